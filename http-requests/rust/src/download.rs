@@ -0,0 +1,229 @@
+use hyper::header::{
+    HeaderMap, HeaderValue, ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE, RANGE,
+};
+use hyper::{Method, StatusCode};
+use rfnd_github::{Error, GithubClient, RateLimit, RequestBody};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::str::from_utf8;
+
+// how many characters the bar of the progress bar has
+const BAR_WIDTH: usize = 30;
+
+/// Where to save a response body, see `--output`.
+#[derive(Debug)]
+pub struct Download {
+    pub path: PathBuf,
+    /// Whether to continue where an earlier download of the same file stopped.
+    pub resume: bool,
+    pub verbose: bool,
+}
+
+impl Download {
+    /// Sends the request and writes the body chunk by chunk to the file, so it never has to fit
+    /// into memory. The body is requested uncompressed, because `Content-Length` and `Range`
+    /// count the bytes as they are sent, and it bypasses the response cache, which would keep a
+    /// second copy of it.
+    pub async fn run(
+        &self,
+        client: &GithubClient,
+        method: Method,
+        path: &str,
+        body: RequestBody,
+    ) -> Result<(), Error> {
+        let offset = match fs::metadata(&self.path) {
+            Ok(metadata) if self.resume => metadata.len(),
+            _ => 0,
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        if offset > 0 {
+            headers.insert(RANGE, format!("bytes={}-", offset).parse().unwrap());
+        }
+        let mut res = client
+            .send_with_headers(method, path, headers, body)
+            .await?;
+
+        let offset = match res.status {
+            StatusCode::PARTIAL_CONTENT => {
+                let start = content_range(&res.headers).and_then(|(start, _)| start);
+                if start != Some(offset) {
+                    let message = format!("server didn't continue at byte {}", offset);
+                    return Err(self.error(io::Error::new(io::ErrorKind::InvalidData, message)));
+                }
+                offset
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE
+                if content_range(&res.headers).map(|(_, total)| total) == Some(Some(offset)) =>
+            {
+                eprintln!("{} is complete already", self.path.display());
+                return Ok(());
+            }
+            status if status.is_success() => {
+                if offset > 0 && self.verbose {
+                    eprintln!("* Server doesn't support ranges, starting over");
+                }
+                0
+            }
+            status => {
+                let rate_limit = RateLimit::from_headers(&res.headers);
+                let body = res.bytes().await?;
+                Error::check_status(status, rate_limit, from_utf8(&body)?)?;
+                // only `304 Not Modified` is left, e.g. for an `If-None-Match` given with `-H`,
+                // and the file mustn't be emptied for it
+                eprintln!("{} is up to date", self.path.display());
+                return Ok(());
+            }
+        };
+
+        let mut file = self.open(offset).map_err(|err| self.error(err))?;
+        let length = res.headers.get(CONTENT_LENGTH).and_then(|length| {
+            length
+                .to_str()
+                .ok()
+                .and_then(|length| length.parse::<u64>().ok())
+        });
+        let mut progress = Progress::new(offset, length.map(|length| offset + length));
+
+        while let Some(chunk) = res.chunk().await? {
+            file.write_all(&chunk).map_err(|err| self.error(err))?;
+            progress.advance(chunk.len() as u64);
+        }
+        progress.finish();
+
+        if self.verbose {
+            eprintln!("* Body: {}", res.body_size());
+        }
+        Ok(())
+    }
+
+    // like `write-files` does, but appends the rest of the body to what is there already
+    fn open(&self, offset: u64) -> io::Result<File> {
+        if offset > 0 {
+            OpenOptions::new().append(true).open(&self.path)
+        } else {
+            File::create(&self.path)
+        }
+    }
+
+    fn error(&self, err: io::Error) -> Error {
+        Error::Output(self.path.clone(), err)
+    }
+}
+
+// the start of the range and the size of the whole body from `bytes START-END/SIZE` or
+// `bytes */SIZE`, either of which can be missing
+fn content_range(headers: &HeaderMap) -> Option<(Option<u64>, Option<u64>)> {
+    let range = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = range.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range
+        .split_once('-')
+        .and_then(|(start, _)| start.parse().ok());
+    Some((start, total.parse().ok()))
+}
+
+/// A progress bar on stderr, which is only drawn if stderr is a terminal.
+struct Progress {
+    done: u64,
+    total: Option<u64>,
+    // what was drawn last, so the line is only redrawn when it changed
+    last: String,
+    is_visible: bool,
+}
+
+impl Progress {
+    fn new(done: u64, total: Option<u64>) -> Self {
+        Progress {
+            done,
+            total,
+            last: String::new(),
+            is_visible: io::stderr().is_terminal(),
+        }
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.done += bytes;
+        if !self.is_visible {
+            return;
+        }
+
+        let line = match self.total {
+            Some(total) if total > 0 => {
+                let ratio = (self.done as f64 / total as f64).min(1.0);
+                let filled = (ratio * BAR_WIDTH as f64) as usize;
+                format!(
+                    "[{}{}] {:>3}% {} of {}",
+                    "#".repeat(filled),
+                    " ".repeat(BAR_WIDTH - filled),
+                    (ratio * 100.0) as u32,
+                    size(self.done),
+                    size(total)
+                )
+            }
+            // without a `Content-Length` there is nothing to compare with
+            _ => size(self.done),
+        };
+        if line != self.last {
+            eprint!("\r{}", line);
+            self.last = line;
+        }
+    }
+
+    fn finish(&self) {
+        if self.is_visible && !self.last.is_empty() {
+            eprintln!();
+        }
+    }
+}
+
+fn size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", value, UNITS[unit]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(content_range: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_RANGE, content_range.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn parses_content_range() {
+        assert_eq!(
+            content_range(&headers("bytes 100-199/300")),
+            Some((Some(100), Some(300)))
+        );
+        assert_eq!(
+            content_range(&headers("bytes 100-199/*")),
+            Some((Some(100), None))
+        );
+        assert_eq!(
+            content_range(&headers("bytes */300")),
+            Some((None, Some(300)))
+        );
+    }
+
+    #[test]
+    fn ignores_other_units() {
+        assert_eq!(content_range(&headers("items 0-9/20")), None);
+        assert_eq!(content_range(&headers("bytes 100-199")), None);
+        assert_eq!(content_range(&HeaderMap::new()), None);
+    }
+}
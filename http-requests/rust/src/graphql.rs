@@ -0,0 +1,87 @@
+use crate::payload::Payload;
+use clap::Args;
+use rfnd_github::cli::check_output;
+use rfnd_github::{Error, GithubClient, PageInfo};
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+
+/// Sends a GraphQL query and prints its data, a page after another.
+#[derive(Args, Debug)]
+pub struct GraphqlArgs {
+    /// The query document, `@FILE` reads it from a file and `@-` from stdin
+    #[arg(value_parser = Payload::parse)]
+    query: Payload,
+
+    /// A variable of the query as `NAME=VALUE`, where VALUE is used as JSON if it is valid JSON
+    /// and as a string otherwise
+    #[arg(short = 'F', long = "field", value_name = "NAME=VALUE", value_parser = parse_variable)]
+    variables: Vec<(String, Value)>,
+
+    /// How many pages are fetched at most, if the query selects a connection with its
+    /// `pageInfo` and passes `$cursor` as its `after` argument
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    max_pages: u32,
+}
+
+/// A query which was read and is ready to be sent.
+#[derive(Debug)]
+pub struct Graphql {
+    query: String,
+    variables: Map<String, Value>,
+    max_pages: u32,
+}
+
+impl GraphqlArgs {
+    /// Reads the query, which fails before anything is sent if e.g. the file doesn't exist.
+    pub fn read(&self) -> Result<Graphql, String> {
+        Ok(Graphql {
+            query: self.query.text()?,
+            variables: self.variables.iter().cloned().collect(),
+            max_pages: self.max_pages,
+        })
+    }
+}
+
+impl Graphql {
+    /// Prints the data of every page as pretty JSON to stdout.
+    pub async fn run(self, client: &GithubClient) -> Result<(), Error> {
+        let mut page = 0;
+        let max_pages = self.max_pages;
+        client
+            .graphql_pages(
+                &self.query,
+                self.variables,
+                find_page_info,
+                |data: Value| {
+                    page += 1;
+                    check_output(writeln!(io::stdout().lock(), "{:#}", data));
+                    match page < max_pages {
+                        true => ControlFlow::Continue(()),
+                        false => ControlFlow::Break(()),
+                    }
+                },
+            )
+            .await
+    }
+}
+
+// the `pageInfo` of the first connection in the data, searched depth-first
+fn find_page_info(data: &Value) -> Option<PageInfo> {
+    match data {
+        Value::Object(fields) => match fields.get("pageInfo") {
+            Some(page_info) => serde_json::from_value(page_info.clone()).ok(),
+            None => fields.values().find_map(find_page_info),
+        },
+        Value::Array(items) => items.iter().find_map(find_page_info),
+        _ => None,
+    }
+}
+
+fn parse_variable(value: &str) -> Result<(String, Value), String> {
+    let (name, value) = value
+        .split_once('=')
+        .ok_or_else(|| "expected `NAME=VALUE`".to_string())?;
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((name.to_string(), value))
+}
@@ -1,39 +1,293 @@
-use hyper::rt::{run, Future, Stream};
-use hyper::{Client, Request};
-use hyper_tls::HttpsConnector;
+mod download;
+mod graphql;
+mod payload;
+mod profile;
+mod trace;
+
+use crate::download::Download;
+use crate::graphql::GraphqlArgs;
+use crate::payload::Payload;
+use crate::profile::Card;
+use crate::trace::{BodyFormat, Trace};
+use clap::{Parser, Subcommand};
+use hyper::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use hyper::Method;
+use rfnd_github::cli::{self, check_output};
+use rfnd_github::{
+    parse_base_url, CacheCommand, CacheOptions, Error, FixtureOptions, GithubClient, RateLimit,
+    RequestBody, RetryPolicy, TimeoutOptions, TlsOptions, Token,
+};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::exit;
 use std::str::from_utf8;
+use std::sync::Arc;
+
+/// Fetches a GitHub user and prints their profile, or sends any request to the GitHub API.
+#[derive(Parser, Debug)]
+#[command(name = "http-requests", version, subcommand_negates_reqs = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The GitHub user to fetch or an API path like `/repos/OWNER/REPO/issues`
+    #[arg(required = true, value_name = "USER|PATH", value_parser = Target::parse)]
+    target: Option<Target>,
+
+    /// Base URL of the API, e.g. of a GitHub Enterprise instance or a local mock
+    #[arg(long, default_value = "https://api.github.com", value_parser = parse_base_url)]
+    base_url: String,
+
+    /// Additional request header as `Name: value` (can be repeated)
+    #[arg(short = 'H', long = "header", value_name = "HEADER", value_parser = parse_header)]
+    headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// HTTP method of the request [default: GET, or POST if there is a body]
+    #[arg(short = 'X', long, value_parser = parse_method)]
+    method: Option<Method>,
+
+    /// Send this as the request body, `@FILE` reads it from a file and `@-` streams it from stdin
+    #[arg(short, long, value_name = "DATA", value_parser = Payload::parse)]
+    data: Option<Payload>,
+
+    /// Send this JSON as the request body with `Content-Type: application/json`, `@FILE` and
+    /// `@-` work like for --data
+    #[arg(long, value_name = "JSON", value_parser = Payload::parse, conflicts_with = "data")]
+    json: Option<Payload>,
+
+    /// How often a request is sent at most when it fails with a temporary error
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// Read the access token from this file instead of `GITHUB_TOKEN` or `~/.netrc`
+    #[arg(long, value_name = "FILE", value_parser = Token::from_file)]
+    token_file: Option<Token>,
+
+    /// Sleep until GitHub's rate limit resets instead of failing when it is exhausted
+    #[arg(long)]
+    wait_for_rate_limit: bool,
 
-fn main() {
-    run(get());
+    /// Print the response body of a user as it is instead of a profile
+    #[arg(long)]
+    raw: bool,
+
+    /// Save the response body to this file instead of printing it, with a progress bar if
+    /// stderr is a terminal
+    #[arg(short, long, value_name = "FILE", conflicts_with_all = ["raw", "trace_body"])]
+    output: Option<PathBuf>,
+
+    /// Continue where an earlier download to the --output file stopped
+    #[arg(short = 'C', long = "continue", requires = "output")]
+    resume: bool,
+
+    /// Print the exchange with the server like `curl -v`, timings and additional information
+    /// like the remaining rate limit to stderr
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Print the response body to stderr as well
+    #[arg(long, value_enum, value_name = "FORMAT", requires = "verbose")]
+    trace_body: Option<BodyFormat>,
+
+    #[command(flatten)]
+    tls: TlsOptions,
+
+    #[command(flatten)]
+    timeouts: TimeoutOptions,
+
+    #[command(flatten)]
+    cache: CacheOptions,
+
+    #[command(flatten)]
+    fixtures: FixtureOptions,
 }
 
-fn get() -> impl Future<Item = (), Error = ()> {
-    // 4 is number of blocking DNS threads
-    let https = HttpsConnector::new(4).unwrap();
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect or clear the response cache
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Send a GraphQL query to `/graphql` and print its data, following the `pageInfo` of the
+    /// first connection in it to further pages
+    Graphql(GraphqlArgs),
+}
 
-    let client = Client::builder().build(https);
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
 
-    let req = Request::get("https://api.github.com/users/donaldpipowitch")
-        .header("User-Agent", "Mercateo/rust-for-node-developers")
-        .body(hyper::Body::empty())
-        .unwrap();
+    if let Some(Command::Cache(command)) = args.command {
+        cli::run_cache_command(&args.cache, command);
+    }
 
-    client
-        .request(req)
-        .and_then(|res| {
-            let status = res.status();
+    let token = Token::resolve(args.token_file.clone(), &args.base_url).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(2);
+    });
 
-            let buf = res.into_body().concat2().wait().unwrap();
-            println!("Response: {}", from_utf8(&buf).unwrap());
+    let graphql = match &args.command {
+        Some(Command::Graphql(graphql)) => Some(graphql.read().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(2);
+        })),
+        _ => None,
+    };
+    let body = request_body(&args).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(2);
+    });
 
-            if status.is_client_error() {
-                panic!("Got client error: {}", status.as_u16());
-            }
-            if status.is_server_error() {
-                panic!("Got server error: {}", status.as_u16());
-            }
+    let deadline = args.timeouts.deadline();
+    let trace = args.verbose.then(|| Arc::new(Trace::new()));
+    let result = match client(&args, token, trace.clone()) {
+        Ok(client) => match graphql {
+            Some(graphql) => deadline.run(graphql.run(&client)).await,
+            None => deadline.run(send(&client, args, body)).await,
+        },
+        Err(err) => Err(err),
+    };
 
-            Ok(())
+    if let Some(trace) = trace {
+        trace.finish();
+    }
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        exit(err.exit_code());
+    }
+}
+
+fn client(
+    args: &Args,
+    token: Option<Token>,
+    trace: Option<Arc<Trace>>,
+) -> Result<GithubClient, Error> {
+    let mut builder = GithubClient::builder()
+        .base_url(&args.base_url)
+        .token(token)
+        .retry(RetryPolicy {
+            wait_for_rate_limit: args.wait_for_rate_limit,
+            ..RetryPolicy::new(args.max_attempts)
         })
-        .map_err(|_err| panic!("Couldn't send request."))
+        .cache(args.cache.cache())
+        .fixtures(args.fixtures.fixtures())
+        .tls(args.tls.clone())
+        .timeouts(args.timeouts.clone());
+    for (name, value) in &args.headers {
+        builder = builder.header(name.clone(), value.clone());
+    }
+    if args.json.is_some() && !args.headers.iter().any(|(name, _)| name == CONTENT_TYPE) {
+        builder = builder.header(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+    if let Some(trace) = trace {
+        builder = builder.trace(move |event| trace.on_event(event));
+    }
+    builder.build()
+}
+
+fn request_body(args: &Args) -> Result<RequestBody, String> {
+    match (&args.data, &args.json) {
+        (Some(data), _) => data.body(),
+        (_, Some(json)) => json.json_body(),
+        (None, None) => Ok(RequestBody::empty()),
+    }
+}
+
+async fn send(client: &GithubClient, args: Args, body: RequestBody) -> Result<(), Error> {
+    let target = args.target.as_ref().unwrap();
+    let method = match &args.method {
+        Some(method) => method.clone(),
+        None if args.data.is_some() || args.json.is_some() => Method::POST,
+        None => Method::GET,
+    };
+    if let Some(output) = &args.output {
+        let download = Download {
+            path: output.clone(),
+            resume: args.resume,
+            verbose: args.verbose,
+        };
+        return download.run(client, method, &target.path(), body).await;
+    }
+
+    let mut res = client.send_body(method, &target.path(), body).await?;
+    let rate_limit = RateLimit::from_headers(&res.headers);
+
+    if args.verbose {
+        if res.from_cache {
+            eprintln!("* Response was served from the cache");
+        }
+        if let Some(rate_limit) = rate_limit {
+            eprintln!("* Rate limit: {}", rate_limit);
+        }
+    }
+
+    let status = res.status;
+    let buf = res.bytes().await?;
+    if args.verbose {
+        eprintln!("* Body: {}", res.body_size());
+    }
+    if let Some(format) = args.trace_body {
+        trace::print_body(&buf, format);
+    }
+    let body = from_utf8(&buf)?;
+
+    Error::check_status(status, rate_limit, body)?;
+
+    let mut stdout = io::stdout().lock();
+    check_output(match target {
+        Target::User(_) if args.raw => writeln!(stdout, "Response: {}", body),
+        Target::User(_) => {
+            let user = serde_json::from_str(body)?;
+            write!(stdout, "{}", Card(&user))
+        }
+        // e.g. `204 No Content` after a DELETE
+        Target::Path(_) if body.is_empty() => Ok(()),
+        Target::Path(_) => writeln!(stdout, "{}", body),
+    });
+    Ok(())
+}
+
+/// What to send the request to: the profile of a user or any path of the API.
+#[derive(Debug, Clone)]
+enum Target {
+    User(String),
+    Path(String),
+}
+
+impl Target {
+    fn parse(value: &str) -> Result<Self, String> {
+        if value.starts_with('/') {
+            return Ok(Target::Path(value.to_string()));
+        }
+        if value.is_empty() || value.contains('/') {
+            return Err(format!(
+                "'{}' is neither a valid GitHub user nor a path starting with '/'",
+                value
+            ));
+        }
+        Ok(Target::User(value.to_string()))
+    }
+
+    fn path(&self) -> String {
+        match self {
+            Target::User(user) => format!("/users/{}", user),
+            Target::Path(path) => path.clone(),
+        }
+    }
+}
+
+fn parse_header(value: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let mut parts = value.splitn(2, ':');
+    let name = parts.next().unwrap_or_default().trim();
+    let value = parts
+        .next()
+        .ok_or_else(|| "expected `Name: value`".to_string())?
+        .trim();
+
+    let name = HeaderName::from_bytes(name.as_bytes()).map_err(|err| format!("{}", err))?;
+    let value = HeaderValue::from_str(value).map_err(|err| format!("{}", err))?;
+    Ok((name, value))
+}
+
+fn parse_method(value: &str) -> Result<Method, String> {
+    Method::from_bytes(value.to_uppercase().as_bytes()).map_err(|err| format!("{}", err))
 }
@@ -0,0 +1,62 @@
+use rfnd_github::RequestBody;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+/// A request body as given to `--data` or `--json`: the text itself, `@FILE` or `@-` for stdin.
+#[derive(Debug, Clone)]
+pub enum Payload {
+    Inline(String),
+    File(PathBuf),
+    Stdin,
+}
+
+impl Payload {
+    /// Used as the clap value parser, which never fails - like curl, a body starting with `@`
+    /// can't be given inline.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        Ok(match value.strip_prefix('@') {
+            Some("-") => Payload::Stdin,
+            Some(path) => Payload::File(path.into()),
+            None => Payload::Inline(value.to_string()),
+        })
+    }
+
+    /// Files are read upfront, so the request can be retried. Stdin is streamed instead, which
+    /// works for bodies of any size, but is only sent once.
+    pub fn body(&self) -> Result<RequestBody, String> {
+        match self {
+            Payload::Stdin => Ok(RequestBody::reader(tokio::io::stdin())),
+            _ => Ok(RequestBody::bytes(self.read()?)),
+        }
+    }
+
+    /// Reads the whole body, even from stdin, to make sure it is valid JSON before it is sent.
+    pub fn json_body(&self) -> Result<RequestBody, String> {
+        let json = self.read()?;
+        serde_json::from_slice::<serde_json::Value>(&json)
+            .map_err(|err| format!("--json isn't valid JSON: {}", err))?;
+        Ok(RequestBody::bytes(json))
+    }
+
+    /// Reads the whole payload as text, e.g. a GraphQL query.
+    pub fn text(&self) -> Result<String, String> {
+        String::from_utf8(self.read()?).map_err(|err| format!("Input isn't valid UTF-8: {}", err))
+    }
+
+    fn read(&self) -> Result<Vec<u8>, String> {
+        match self {
+            Payload::Inline(text) => Ok(text.clone().into_bytes()),
+            Payload::File(path) => {
+                fs::read(path).map_err(|err| format!("Couldn't read {}: {}", path.display(), err))
+            }
+            Payload::Stdin => {
+                let mut buf = Vec::new();
+                io::stdin()
+                    .read_to_end(&mut buf)
+                    .map_err(|err| format!("Couldn't read stdin: {}", err))?;
+                Ok(buf)
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use rfnd_github::User;
+use std::fmt;
+
+/// Renders a user as a short profile for humans. Fields the user didn't fill in are left out.
+pub struct Card<'a>(pub &'a User);
+
+impl fmt::Display for Card<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let user = self.0;
+
+        match non_empty(&user.name) {
+            Some(name) => writeln!(f, "{} ({})", name, user.login)?,
+            None => writeln!(f, "{}", user.login)?,
+        }
+        writeln!(f, "{}", user.html_url)?;
+
+        let details = [
+            ("Company", &user.company),
+            ("Blog", &user.blog),
+            ("Location", &user.location),
+            ("Bio", &user.bio),
+        ];
+        if details.iter().any(|(_, value)| non_empty(value).is_some()) {
+            writeln!(f)?;
+        }
+        for (label, value) in details {
+            if let Some(value) = non_empty(value) {
+                // bios often contain line breaks, which would break the layout
+                let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+                writeln!(f, "{:<10} {}", label, value)?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{:<10} {}", "Repos", user.public_repos)?;
+        writeln!(f, "{:<10} {}", "Followers", user.followers)?;
+        writeln!(f, "{:<10} {}", "Following", user.following)?;
+        writeln!(f, "{:<10} {}", "Joined", user.created_at.format("%Y-%m-%d"))
+    }
+}
+
+// GitHub uses `null` as well as `""` for fields which aren't set
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
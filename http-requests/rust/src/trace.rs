@@ -0,0 +1,166 @@
+use clap::ValueEnum;
+use hyper::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use rfnd_github::Event;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// how many bytes are shown per line of a hex dump
+const HEX_WIDTH: usize = 16;
+
+/// Prints the exchange with the server to stderr like `curl -v` does: `>` marks what is sent,
+/// `<` what is received and `*` everything else.
+pub struct Trace {
+    start: Instant,
+    timings: Mutex<Timings>,
+}
+
+// when each phase ended, measured from the start like curl does
+#[derive(Default)]
+struct Timings {
+    dns: Option<Duration>,
+    connect: Option<Duration>,
+    tls: Option<Duration>,
+    first_byte: Option<Duration>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyFormat {
+    /// Indented JSON, text as it is and a hex dump for anything else
+    Pretty,
+    /// A hex dump with offsets and the printable characters
+    Hex,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Trace {
+            start: Instant::now(),
+            timings: Mutex::new(Timings::default()),
+        }
+    }
+
+    pub fn on_event(&self, event: &Event) {
+        let elapsed = self.start.elapsed();
+        let mut timings = self.timings.lock().unwrap();
+
+        match event {
+            Event::Request {
+                method,
+                uri,
+                headers,
+            } => {
+                eprintln!("> {} {}", method, uri);
+                print_headers('>', headers);
+            }
+            Event::Resolved { host, addrs } => {
+                timings.dns = Some(elapsed);
+                eprintln!("* Resolved {} to {} ({})", host, join(addrs), ms(elapsed));
+            }
+            Event::Connected { addr } => {
+                timings.connect = Some(elapsed);
+                eprintln!("* Connected to {} ({})", addr, ms(elapsed));
+            }
+            Event::TlsHandshake => {
+                timings.tls = Some(elapsed);
+                eprintln!("* TLS handshake done ({})", ms(elapsed));
+            }
+            Event::Response {
+                version,
+                status,
+                headers,
+            } => {
+                timings.first_byte = Some(elapsed);
+                eprintln!("< {:?} {}", version, status);
+                print_headers('<', headers);
+            }
+        }
+    }
+
+    /// Prints when each phase of the exchange ended. Phases which didn't happen (e.g. because
+    /// a pooled connection was used) are shown as `-`.
+    pub fn finish(&self) {
+        let total = self.start.elapsed();
+        let timings = self.timings.lock().unwrap();
+        let phase = |elapsed: Option<Duration>| elapsed.map(ms).unwrap_or_else(|| "-".into());
+
+        eprintln!(
+            "* Timing: DNS {}, connect {}, TLS {}, first byte {}, total {}",
+            phase(timings.dns),
+            phase(timings.connect),
+            phase(timings.tls),
+            phase(timings.first_byte),
+            ms(total)
+        );
+    }
+}
+
+/// Prints a body to stderr.
+pub fn print_body(body: &[u8], format: BodyFormat) {
+    let text = match format {
+        BodyFormat::Pretty => match serde_json::from_slice::<serde_json::Value>(body) {
+            Ok(json) => serde_json::to_string_pretty(&json).ok(),
+            Err(_) => std::str::from_utf8(body).ok().map(str::to_string),
+        },
+        BodyFormat::Hex => None,
+    };
+
+    match text {
+        Some(text) => eprintln!("{}", text),
+        None => eprint!("{}", hex_dump(body)),
+    }
+}
+
+// looks like `xxd`: the offset, the bytes in hex and the printable ones as they are
+fn hex_dump(body: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, bytes) in body.chunks(HEX_WIDTH).enumerate() {
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let printable: String = bytes
+            .iter()
+            .map(|&byte| match byte {
+                b' '..=b'~' => byte as char,
+                _ => '.',
+            })
+            .collect();
+
+        let _ = writeln!(
+            dump,
+            "{:08x}  {:<width$}  |{}|",
+            line * HEX_WIDTH,
+            hex.join(" "),
+            printable,
+            width = HEX_WIDTH * 3 - 1
+        );
+    }
+    dump
+}
+
+fn print_headers(prefix: char, headers: &HeaderMap) {
+    for (name, value) in headers {
+        let value = match name {
+            &AUTHORIZATION => redact(value),
+            _ => String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        };
+        eprintln!("{} {}: {}", prefix, name, value);
+    }
+    eprintln!("{}", prefix);
+}
+
+// keeps the scheme, so it is still visible which kind of credentials were sent
+fn redact(value: &HeaderValue) -> String {
+    match value.to_str().ok().and_then(|value| value.split_once(' ')) {
+        Some((scheme, _)) => format!("{} [redacted]", scheme),
+        None => "[redacted]".to_string(),
+    }
+}
+
+fn join(addrs: &[SocketAddr]) -> String {
+    let ips: Vec<String> = addrs.iter().map(|addr| addr.ip().to_string()).collect();
+    ips.join(", ")
+}
+
+fn ms(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,882 @@
+use rfnd_github_mock::{archive, stderr, stdout, Example, MockGithub, MockProxy};
+use std::io::Write;
+use std::net::TcpListener;
+use std::process::{Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const EXAMPLE: Example = Example::new(
+    env!("CARGO_BIN_EXE_http-requests"),
+    env!("CARGO_TARGET_TMPDIR"),
+);
+
+fn run_cached(server: &MockGithub, cache_dir: &str, args: &[&str]) -> Output {
+    EXAMPLE
+        .cached_command(server, Some(cache_dir), args)
+        .output()
+        .expect("Couldn't run http-requests")
+}
+
+fn run_with_stdin(server: &MockGithub, args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = EXAMPLE
+        .command(server, args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Couldn't run http-requests");
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn prints_profile() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["octocat"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let profile = stdout(&output);
+    assert!(profile.starts_with("The Octocat (octocat)\nhttps://github.com/octocat\n"));
+    assert!(profile.contains("Location   San Francisco\n"));
+    assert!(profile.contains("Joined     2011-01-25\n"));
+    // empty fields are left out
+    assert!(!profile.contains("Blog"));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, "/users/octocat");
+    assert_eq!(
+        requests[0].header("user-agent"),
+        Some("Mercateo/rust-for-node-developers")
+    );
+    assert_eq!(requests[0].header("authorization"), None);
+}
+
+#[test]
+fn prints_raw_body() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["--raw", "octocat"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).starts_with("Response: {"));
+}
+
+#[test]
+fn exits_quietly_if_stdout_is_closed() {
+    let server = MockGithub::start();
+    let mut child = EXAMPLE
+        .command(&server, &["octocat"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Couldn't run http-requests");
+    // like `| head -0`, which doesn't read anything
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn sends_token_and_headers() {
+    let server = MockGithub::start();
+    let token = EXAMPLE.token_file("sends_token_and_headers");
+    let output = EXAMPLE.run(
+        &server,
+        &["--token-file", &token, "-H", "X-Test: yes", "octocat"],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let requests = server.requests();
+    assert_eq!(requests[0].header("authorization"), Some("Bearer secret"));
+    assert_eq!(requests[0].header("x-test"), Some("yes"));
+}
+
+#[test]
+fn tunnels_through_proxy_with_credentials() {
+    let server = MockGithub::start();
+    let proxy = MockProxy::start();
+    let output = EXAMPLE
+        .command(&server, &["octocat"])
+        .env("HTTP_PROXY", format!("http://user:pass@{}", proxy.addr()))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let tunnels = proxy.requests();
+    assert_eq!(tunnels.len(), 1);
+    assert_eq!(tunnels[0].method, "CONNECT");
+    assert_eq!(tunnels[0].path, server.url().trim_start_matches("http://"));
+    // base64 of `user:pass`
+    assert_eq!(
+        tunnels[0].header("proxy-authorization"),
+        Some("Basic dXNlcjpwYXNz")
+    );
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn bypasses_proxy_for_no_proxy_hosts() {
+    let server = MockGithub::start();
+    let proxy = MockProxy::start();
+    let output = EXAMPLE
+        .command(&server, &["octocat"])
+        .env("HTTP_PROXY", format!("http://{}", proxy.addr()))
+        .env("NO_PROXY", "example.com,127.0.0.1")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(proxy.requests().is_empty());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn fails_for_missing_user() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["missing"]);
+
+    assert_eq!(output.status.code(), Some(6));
+    assert!(stderr(&output).contains("404"), "{}", stderr(&output));
+    assert!(stdout(&output).is_empty());
+}
+
+#[test]
+fn fails_for_server_error_without_retrying() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["broken"]);
+
+    assert_eq!(output.status.code(), Some(7));
+    assert!(stderr(&output).contains("500"), "{}", stderr(&output));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn retries_unavailable_server() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["--max-attempts", "3", "unavailable"]);
+
+    assert_eq!(output.status.code(), Some(7));
+    assert!(stderr(&output).contains("retrying"), "{}", stderr(&output));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn times_out_for_stalled_server() {
+    let server = MockGithub::start();
+    let started = Instant::now();
+    let output = EXAMPLE.run(&server, &["--read-timeout", "0.3", "stalled"]);
+
+    assert_eq!(output.status.code(), Some(12));
+    assert!(stderr(&output).contains("Timed out: the server sent nothing for 0.3s"));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn times_out_for_whole_request() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["--timeout", "0.3", "stalled"]);
+
+    assert_eq!(output.status.code(), Some(12));
+    assert!(stderr(&output).contains("Timed out: didn't finish within 0.3s"));
+}
+
+#[test]
+fn fails_for_refused_connection() {
+    // nobody listens at the port once the listener is dropped
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let output = EXAMPLE
+        .command_at(
+            &format!("http://{}", addr),
+            None,
+            &["--max-attempts", "1", "octocat"],
+        )
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).starts_with("Couldn't connect: "));
+    assert!(stderr(&output).contains("Connection refused"));
+}
+
+#[test]
+fn fails_for_tls_handshake_with_plain_http_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n");
+        }
+    });
+    let output = EXAMPLE
+        .command_at(&format!("https://{}", addr), None, &["octocat"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(stderr(&output).starts_with("TLS error"));
+}
+
+#[test]
+fn rejects_invalid_cacert() {
+    let server = MockGithub::start();
+    let missing = EXAMPLE.tmp_path("missing.pem");
+    let output = EXAMPLE.run(&server, &["--cacert", &missing, "octocat"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Couldn't read"));
+
+    let empty = EXAMPLE.tmp_path("empty.pem");
+    std::fs::write(&empty, "no certificates here\n").unwrap();
+    let output = EXAMPLE.run(&server, &["--cacert", &empty, "octocat"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("doesn't contain any certificates"));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn fails_for_malformed_json() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["malformed"]);
+
+    assert_eq!(output.status.code(), Some(9));
+    assert!(stdout(&output).is_empty());
+}
+
+#[test]
+fn fails_for_exhausted_rate_limit() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["limited"]);
+
+    assert_eq!(output.status.code(), Some(10));
+    assert!(
+        stderr(&output).contains("rate limit"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn fails_for_exhausted_rate_limit_without_waiting() {
+    let server = MockGithub::start();
+    let started = Instant::now();
+    let output = EXAMPLE.run(&server, &["throttled"]);
+
+    // the rate limit resets in an hour, which must not be waited for without being asked to
+    assert_eq!(output.status.code(), Some(10), "{}", stderr(&output));
+    assert_eq!(server.requests().len(), 1);
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn fails_for_missing_user_with_exhausted_rate_limit() {
+    let server = MockGithub::start();
+    let started = Instant::now();
+    let output = EXAMPLE.run(&server, &["--wait-for-rate-limit", "depleted"]);
+
+    // only a `403` or `429` is refused because of the rate limit, a `404` isn't worth waiting for
+    assert_eq!(output.status.code(), Some(6), "{}", stderr(&output));
+    assert_eq!(server.requests().len(), 1);
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn replays_recorded_responses() {
+    let server = MockGithub::start();
+    let dir = EXAMPLE.tmp_path("fixtures");
+
+    let recorded = EXAMPLE.run(&server, &["--record", &dir, "octocat"]);
+    assert!(recorded.status.success(), "{}", stderr(&recorded));
+
+    let replayed = EXAMPLE.run(&server, &["--replay", &dir, "octocat"]);
+    assert!(replayed.status.success(), "{}", stderr(&replayed));
+    assert_eq!(stdout(&replayed), stdout(&recorded));
+    assert_eq!(server.requests().len(), 1);
+
+    let unknown = EXAMPLE.run(&server, &["--replay", &dir, "github"]);
+    assert_eq!(unknown.status.code(), Some(13));
+    assert!(stderr(&unknown).contains("No response recorded for GET /users/github"));
+}
+
+#[test]
+fn replays_responses_by_request_body() {
+    let server = MockGithub::start();
+    let dir = EXAMPLE.tmp_path("body-fixtures");
+    let token = EXAMPLE.token_file("replays_responses_by_request_body");
+    let issue = |fixtures: &str, title: &str| {
+        let json = format!(r#"{{"title": "{}"}}"#, title);
+        let args = ["--token-file", &token, fixtures, &dir, "--json", &json];
+        EXAMPLE.run(
+            &server,
+            &[&args[..], &["/repos/octocat/hello-world/issues"]].concat(),
+        )
+    };
+
+    for title in ["First", "Second"] {
+        let recorded = issue("--record", title);
+        assert!(recorded.status.success(), "{}", stderr(&recorded));
+    }
+    for title in ["Second", "First"] {
+        let replayed = issue("--replay", title);
+        assert!(replayed.status.success(), "{}", stderr(&replayed));
+        assert!(stdout(&replayed).contains(&format!(r#""title":"{}""#, title)));
+    }
+    // a streamed body is matched as well
+    let streamed = run_with_stdin(
+        &server,
+        &[
+            "--replay",
+            &dir,
+            "--data",
+            "@-",
+            "/repos/octocat/hello-world/issues",
+        ],
+        br#"{"title": "First"}"#,
+    );
+    assert!(streamed.status.success(), "{}", stderr(&streamed));
+    assert!(stdout(&streamed).contains(r#""title":"First""#));
+    assert_eq!(server.requests().len(), 2);
+
+    let unknown = issue("--replay", "Third");
+    assert_eq!(unknown.status.code(), Some(13));
+    assert!(stderr(&unknown)
+        .contains("No response recorded for POST /repos/octocat/hello-world/issues"));
+}
+
+#[test]
+fn revalidates_cached_response() {
+    let server = MockGithub::start();
+    let cache = EXAMPLE.tmp_path("revalidates_cached_response.cache");
+    let first = run_cached(&server, &cache, &["octocat"]);
+    let second = run_cached(&server, &cache, &["-v", "octocat"]);
+
+    assert!(second.status.success(), "{}", stderr(&second));
+    assert_eq!(stdout(&second), stdout(&first));
+    assert!(stderr(&second).contains("* Response was served from the cache"));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].header("if-none-match"), None);
+    assert!(requests[1].header("if-none-match").is_some());
+}
+
+#[cfg(unix)]
+#[test]
+fn keeps_cache_private() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    let server = MockGithub::start();
+    let cache = EXAMPLE.tmp_path("keeps_cache_private.cache");
+    fs::create_dir(&cache).unwrap();
+    fs::set_permissions(&cache, fs::Permissions::from_mode(0o755)).unwrap();
+    let token = EXAMPLE.token_file("keeps_cache_private");
+    let output = run_cached(&server, &cache, &["--token-file", &token, "octocat"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(Path::new(&cache)), 0o700);
+    let files: Vec<_> = fs::read_dir(&cache)
+        .unwrap()
+        .map(|file| file.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 2);
+    for file in files {
+        assert_eq!(mode(&file), 0o600, "{}", file.display());
+    }
+}
+
+#[test]
+fn caches_representations_separately() {
+    let server = MockGithub::start();
+    let cache = EXAMPLE.tmp_path("caches_representations_separately.cache");
+    run_cached(&server, &cache, &["--cache-ttl", "60", "--raw", "octocat"]);
+    let output = run_cached(
+        &server,
+        &cache,
+        &[
+            "--cache-ttl",
+            "60",
+            "-v",
+            "-H",
+            "Accept: application/vnd.github.raw",
+            "--raw",
+            "octocat",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("served from the cache"));
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].header("if-none-match"), None);
+    assert_eq!(
+        requests[1].header("accept"),
+        Some("application/vnd.github.raw")
+    );
+
+    // the first representation is still cached next to the second one
+    run_cached(&server, &cache, &["--cache-ttl", "60", "--raw", "octocat"]);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn decompresses_body() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["--verbose", "octocat"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).starts_with("The Octocat (octocat)\n"));
+    assert!(
+        stderr(&output).contains("bytes compressed, "),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(
+        server.requests()[0].header("accept-encoding"),
+        Some("br, gzip, deflate")
+    );
+}
+
+#[test]
+fn traces_exchange() {
+    let server = MockGithub::start();
+    let token = EXAMPLE.tmp_path("trace-token");
+    std::fs::write(&token, "secret\n").unwrap();
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "-v",
+            "--trace-body",
+            "hex",
+            "--token-file",
+            &token,
+            "octocat",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let trace = stderr(&output);
+    assert!(trace.contains(&format!("> GET {}/users/octocat\n", server.url())));
+    assert!(trace.contains("> authorization: Bearer [redacted]\n"));
+    assert!(!trace.contains("secret"));
+    assert!(trace.contains("* Connected to "));
+    assert!(trace.contains("< HTTP/1.1 200 OK\n"));
+    assert!(trace.contains("< content-encoding: gzip\n"));
+    assert!(trace.contains("00000000  7b 22 6c 6f 67 69 6e 22"));
+    assert!(trace.contains("* Timing: DNS -, connect "));
+}
+
+#[test]
+fn creates_issue_with_json() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("creates_issue_with_json"),
+            "--json",
+            r#"{"title": "Found a bug", "body": "It crashes"}"#,
+            "/repos/octocat/hello-world/issues",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains(r#""number":1347"#));
+    assert!(stdout(&output).contains(r#""title":"Found a bug""#));
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].header("content-type"), Some("application/json"));
+    assert_eq!(
+        requests[0].body,
+        br#"{"title": "Found a bug", "body": "It crashes"}"#
+    );
+}
+
+#[test]
+fn follows_redirect_of_renamed_repository() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["/repos/octocat/moved"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains(r#""full_name":"octocat/hello-world""#));
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].path, "/repos/octocat/hello-world");
+}
+
+#[test]
+fn sends_body_again_after_redirect() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("sends_body_again_after_redirect"),
+            "--json",
+            r#"{"title": "Found a bug"}"#,
+            "/repos/octocat/moved/issues",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains(r#""number":1347"#));
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].method, "POST");
+    assert_eq!(requests[1].path, "/repos/octocat/hello-world/issues");
+    // the redirect stays on the same host, so the token is still sent
+    assert!(requests[1].header("authorization").is_some());
+    assert_eq!(requests[1].body, br#"{"title": "Found a bug"}"#);
+}
+
+#[test]
+fn fails_for_redirect_of_streamed_body() {
+    let server = MockGithub::start();
+    let output = run_with_stdin(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("fails_for_redirect_of_streamed_body"),
+            "--data",
+            "@-",
+            "/repos/octocat/moved/issues",
+        ],
+        br#"{"title": "From stdin"}"#,
+    );
+
+    assert_eq!(output.status.code(), Some(16));
+    assert!(
+        stderr(&output).contains("Couldn't follow redirect: the body can't be sent to"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn fails_for_redirect_loop() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["/repos/octocat/circular"]);
+
+    assert_eq!(output.status.code(), Some(16));
+    assert!(
+        stderr(&output).contains("Couldn't follow redirect: more than 10 redirects"),
+        "{}",
+        stderr(&output)
+    );
+    assert!(stdout(&output).is_empty());
+    assert_eq!(server.requests().len(), 11);
+}
+
+#[test]
+fn sends_data_from_file() {
+    let server = MockGithub::start();
+    let issue = EXAMPLE.tmp_path("issue.json");
+    std::fs::write(&issue, r#"{"title": "From a file"}"#).unwrap();
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("sends_data_from_file"),
+            "-X",
+            "post",
+            "--data",
+            &format!("@{}", issue),
+            "/repos/octocat/hello-world/issues",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].header("content-length"), Some("24"));
+    // unlike --json, --data sends no content type of its own
+    assert_eq!(requests[0].header("content-type"), None);
+}
+
+#[test]
+fn streams_data_from_stdin() {
+    let server = MockGithub::start();
+    let output = run_with_stdin(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("streams_data_from_stdin"),
+            "--data",
+            "@-",
+            "/repos/octocat/hello-world/issues",
+        ],
+        br#"{"title": "From stdin"}"#,
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let requests = server.requests();
+    assert_eq!(requests[0].header("transfer-encoding"), Some("chunked"));
+    assert_eq!(requests[0].body, br#"{"title": "From stdin"}"#);
+}
+
+#[test]
+fn rejects_invalid_json() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(
+        &server,
+        &["--json", "{title}", "/repos/octocat/hello-world/issues"],
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("--json isn't valid JSON"));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn deletes_repository() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("deletes_repository"),
+            "-X",
+            "DELETE",
+            "/repos/octocat/hello-world",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).is_empty());
+    assert_eq!(server.requests()[0].method, "DELETE");
+}
+
+#[test]
+fn downloads_to_file() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.tmp_path("downloads.tar.gz");
+    let cache = EXAMPLE.tmp_path("downloads_to_file.cache");
+    let result = run_cached(
+        &server,
+        &cache,
+        &["-o", &output, "/repos/octocat/hello-world/tarball"],
+    );
+
+    assert!(result.status.success(), "{}", stderr(&result));
+    assert!(stdout(&result).is_empty());
+    assert!(std::fs::read(&output).unwrap() == archive());
+    // the archive is served by another host, like `codeload.github.com`
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[1].path,
+        "/codeload/octocat/hello-world/legacy.tar.gz"
+    );
+    // otherwise `Content-Length` wouldn't tell how much is written to the file
+    assert_eq!(requests[1].header("accept-encoding"), Some("identity"));
+    // the file is the only copy of the body
+    let cached = std::fs::read_dir(&cache).map_or(0, |entries| entries.count());
+    assert_eq!(cached, 0);
+}
+
+#[test]
+fn resumes_download() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.tmp_path("resumes.tar.gz");
+    std::fs::write(&output, &archive()[..100_000]).unwrap();
+    let result = EXAMPLE.run(
+        &server,
+        &["-C", "-o", &output, "/repos/octocat/hello-world/tarball"],
+    );
+
+    assert!(result.status.success(), "{}", stderr(&result));
+    assert!(std::fs::read(&output).unwrap() == archive());
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    // the range is asked for again after the redirect
+    assert_eq!(requests[1].header("range"), Some("bytes=100000-"));
+}
+
+#[test]
+fn keeps_token_from_archive_host() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.tmp_path("private.tar.gz");
+    let result = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("keeps_token_from_archive_host"),
+            "-o",
+            &output,
+            "/repos/octocat/hello-world/tarball",
+        ],
+    );
+
+    assert!(result.status.success(), "{}", stderr(&result));
+    assert!(std::fs::read(&output).unwrap() == archive());
+    let requests = server.requests();
+    assert!(requests[0].header("authorization").is_some());
+    assert!(requests[1]
+        .header("host")
+        .unwrap()
+        .starts_with("localhost:"));
+    assert_eq!(requests[1].header("authorization"), None);
+}
+
+#[test]
+fn keeps_file_if_not_modified() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.tmp_path("unchanged.tar.gz");
+    let first = EXAMPLE.run(
+        &server,
+        &["-v", "-o", &output, "/repos/octocat/hello-world/tarball"],
+    );
+    let etag = stderr(&first)
+        .lines()
+        .find_map(|line| line.strip_prefix("< etag: ").map(str::to_string))
+        .unwrap();
+    std::fs::write(&output, "kept").unwrap();
+    let second = EXAMPLE.run(
+        &server,
+        &[
+            "-H",
+            &format!("If-None-Match: {}", etag),
+            "-o",
+            &output,
+            "/repos/octocat/hello-world/tarball",
+        ],
+    );
+
+    assert!(second.status.success(), "{}", stderr(&second));
+    assert!(stderr(&second).contains("is up to date"));
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "kept");
+}
+
+#[test]
+fn resumes_complete_download() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.tmp_path("complete.tar.gz");
+    std::fs::write(&output, archive()).unwrap();
+    let result = EXAMPLE.run(
+        &server,
+        &["-C", "-o", &output, "/repos/octocat/hello-world/tarball"],
+    );
+
+    assert!(result.status.success(), "{}", stderr(&result));
+    assert!(stderr(&result).contains("is complete already"));
+    assert!(std::fs::read(&output).unwrap() == archive());
+}
+
+#[test]
+fn follows_graphql_pages() {
+    let server = MockGithub::start();
+    let query = "query($login: String!, $first: Int!, $cursor: String) { \
+        repositoryOwner(login: $login) { repositories(first: $first, after: $cursor) { \
+        nodes { name } pageInfo { hasNextPage endCursor } } } }";
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("follows_graphql_pages"),
+            "graphql",
+            query,
+            "-F",
+            "login=octocat",
+            "-F",
+            "first=2",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let pages: Vec<serde_json::Value> = serde_json::Deserializer::from_slice(&output.stdout)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(pages.len(), 2);
+    let nodes = &pages[1]["repositoryOwner"]["repositories"]["nodes"];
+    assert_eq!(nodes[0]["name"], "linguist");
+
+    let requests = server.requests();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(requests[1].header("content-type"), Some("application/json"));
+    assert_eq!(body["query"], query);
+    // numbers are sent as numbers, everything else as a string
+    assert_eq!(body["variables"]["first"], 2);
+    assert_eq!(body["variables"]["cursor"], "cursor:2");
+}
+
+#[test]
+fn stops_if_graphql_query_ignores_cursor() {
+    let server = MockGithub::start();
+    // has a `pageInfo`, but always asks for the first page
+    let query = "query($login: String!, $first: Int!) { repositoryOwner(login: $login) { \
+        repositories(first: $first) { nodes { name } pageInfo { hasNextPage endCursor } } } }";
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("stops_if_graphql_query_ignores_cursor"),
+            "graphql",
+            query,
+            "-F",
+            "login=octocat",
+            "-F",
+            "first=2",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let pages = serde_json::Deserializer::from_slice(&output.stdout)
+        .into_iter::<serde_json::Value>()
+        .count();
+    assert_eq!(pages, 1);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn exits_quietly_if_graphql_output_is_closed() {
+    let server = MockGithub::start();
+    let token = EXAMPLE.token_file("exits_quietly_if_graphql_output_is_closed");
+    let query = "query($login: String!) { repositoryOwner(login: $login) { login } }";
+    let mut child = EXAMPLE
+        .command(
+            &server,
+            &[
+                "--token-file",
+                &token,
+                "graphql",
+                query,
+                "-F",
+                "login=octocat",
+            ],
+        )
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Couldn't run http-requests");
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn fails_for_graphql_errors() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &EXAMPLE.token_file("fails_for_graphql_errors"),
+            "graphql",
+            "query { viewer { login } }",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(15));
+    assert!(stderr(&output).contains("GraphQL query failed:\nVariable $login"));
+}
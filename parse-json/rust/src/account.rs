@@ -0,0 +1,55 @@
+use std::fmt;
+
+/// A GitHub account whose repositories are listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Account {
+    User(String),
+    Organization(String),
+    /// The authenticated user, including private repositories.
+    Own,
+}
+
+impl Account {
+    /// Parses `<user>` or `org:<organization>`, used as the clap value parser.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (login, account) = match value.strip_prefix("org:") {
+            Some(login) => (login, Account::Organization(login.to_string())),
+            None => (value, Account::User(value.to_string())),
+        };
+
+        // GitHub logins only consist of alphanumeric characters and hyphens
+        if login.is_empty() || !login.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("`{}` isn't a valid GitHub login", login));
+        }
+
+        Ok(account)
+    }
+
+    /// The path of the first page of repositories.
+    pub fn path(&self, per_page: u32) -> String {
+        match self {
+            Account::User(login) => format!("/users/{}/repos?per_page={}", login, per_page),
+            Account::Organization(login) => format!("/orgs/{}/repos?per_page={}", login, per_page),
+            // only this endpoint includes private repositories
+            Account::Own => format!("/user/repos?affiliation=owner&per_page={}", per_page),
+        }
+    }
+
+    /// The login of the user or organization, which isn't known for [`Account::Own`].
+    pub fn login(&self) -> Option<&str> {
+        match self {
+            Account::User(login) | Account::Organization(login) => Some(login),
+            Account::Own => None,
+        }
+    }
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Account::User(login) => write!(f, "{}", login),
+            Account::Organization(login) => write!(f, "org:{}", login),
+            Account::Own => write!(f, "the authenticated user"),
+        }
+    }
+}
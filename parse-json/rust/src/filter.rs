@@ -0,0 +1,253 @@
+use crate::output::Tagged;
+use clap::ValueEnum;
+use regex::Regex;
+use rfnd_github::Repository;
+use std::cmp::Ordering;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Alphabetically
+    Name,
+    /// Most stars first
+    Stars,
+    /// Most recently updated first
+    Updated,
+}
+
+/// Which of the fetched repositories are printed and in which order.
+#[derive(clap::Args, Debug, Clone)]
+pub struct Query {
+    /// Skip forks
+    #[arg(long, conflicts_with = "only_forks")]
+    pub no_forks: bool,
+
+    /// Only show forks
+    #[arg(long)]
+    pub only_forks: bool,
+
+    /// Skip repositories without a description
+    #[arg(long)]
+    pub has_description: bool,
+
+    /// Only show repositories whose name matches this regular expression
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    pub name_matches: Option<Regex>,
+
+    /// Sort the repositories
+    #[arg(long, value_enum)]
+    pub sort: Option<SortKey>,
+
+    /// Reverse the order
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Show at most this many repositories
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    pub fn matches(&self, repository: &Repository) -> bool {
+        if self.no_forks && repository.fork {
+            return false;
+        }
+        if self.only_forks && !repository.fork {
+            return false;
+        }
+        if self.has_description && repository.description.as_deref().unwrap_or("").is_empty() {
+            return false;
+        }
+        if let Some(regex) = &self.name_matches {
+            return regex.is_match(&repository.name);
+        }
+        true
+    }
+
+    /// Whether repositories can be shown one by one as they arrive, which isn't possible if they
+    /// need to be sorted.
+    pub fn is_streamable(&self) -> bool {
+        self.sort.is_none() && !self.reverse
+    }
+
+    /// Whether `--limit` is reached after showing this many repositories, which ends a stream.
+    pub fn is_limit_reached(&self, shown: usize) -> bool {
+        self.limit.is_some_and(|limit| shown >= limit)
+    }
+
+    /// Filters, sorts and limits the repositories - in this order.
+    pub fn apply(&self, repositories: Vec<Tagged>) -> Vec<Tagged> {
+        let mut repositories: Vec<Tagged> = repositories
+            .into_iter()
+            .filter(|tagged| self.matches(&tagged.repository))
+            .collect();
+
+        if let Some(key) = self.sort {
+            repositories.sort_by(|a, b| compare(key, &a.repository, &b.repository));
+        }
+        if self.reverse {
+            repositories.reverse();
+        }
+        if let Some(limit) = self.limit {
+            repositories.truncate(limit);
+        }
+
+        repositories
+    }
+}
+
+fn compare(key: SortKey, a: &Repository, b: &Repository) -> Ordering {
+    match key {
+        SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        SortKey::Stars => b.stargazers_count.cmp(&a.stargazers_count),
+        SortKey::Updated => b.updated_at.cmp(&a.updated_at),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use serde_json::json;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        query: Query,
+    }
+
+    fn query(args: &[&str]) -> Query {
+        Cli::parse_from([&["parse-json"], args].concat()).query
+    }
+
+    fn repository(name: &str, fork: bool, description: Option<&str>, stars: u64) -> Tagged {
+        let repository = json!({
+            "id": 1,
+            "name": name,
+            "full_name": format!("octocat/{}", name),
+            "html_url": format!("https://github.com/octocat/{}", name),
+            "description": description,
+            "fork": fork,
+            "owner": {
+                "login": "octocat",
+                "id": 1,
+                "html_url": "https://github.com/octocat",
+                "type": "User"
+            },
+            "license": null,
+            "stargazers_count": stars,
+            "language": null,
+            "created_at": "2011-01-26T19:01:12Z",
+            "updated_at": "2011-01-26T19:14:43Z",
+            "pushed_at": null,
+            "archived": false,
+            "visibility": "public"
+        });
+        Tagged {
+            repository: serde_json::from_value(repository).unwrap(),
+            account: "octocat".to_string(),
+        }
+    }
+
+    fn repositories() -> Vec<Tagged> {
+        vec![
+            repository("hello-world", false, Some("My first repository"), 42),
+            repository("Spoon-Knife", true, Some("For testing forks"), 7),
+            repository("linguist", true, None, 0),
+            repository("docs", false, Some(""), 100),
+        ]
+    }
+
+    fn names(repositories: &[Tagged]) -> Vec<&str> {
+        repositories
+            .iter()
+            .map(|tagged| tagged.repository.name.as_str())
+            .collect()
+    }
+
+    fn matching(query: &Query) -> Vec<String> {
+        repositories()
+            .into_iter()
+            .filter(|tagged| query.matches(&tagged.repository))
+            .map(|tagged| tagged.repository.name)
+            .collect()
+    }
+
+    #[test]
+    fn matches_everything_without_filters() {
+        assert_eq!(matching(&query(&[])).len(), 4);
+    }
+
+    #[test]
+    fn filters_forks() {
+        assert_eq!(matching(&query(&["--no-forks"])), ["hello-world", "docs"]);
+        assert_eq!(
+            matching(&query(&["--only-forks"])),
+            ["Spoon-Knife", "linguist"]
+        );
+    }
+
+    #[test]
+    fn skips_missing_and_empty_descriptions() {
+        assert_eq!(
+            matching(&query(&["--has-description"])),
+            ["hello-world", "Spoon-Knife"]
+        );
+    }
+
+    #[test]
+    fn filters_names_after_other_filters() {
+        assert_eq!(
+            matching(&query(&["--name-matches", "^[a-z]+$"])),
+            ["linguist", "docs"]
+        );
+        // the regex doesn't bring back what the filters before it skipped
+        assert_eq!(
+            matching(&query(&["--no-forks", "--name-matches", "^[a-z]+$"])),
+            ["docs"]
+        );
+    }
+
+    #[test]
+    fn sorts_names_case_insensitively() {
+        let sorted = query(&["--sort", "name"]).apply(repositories());
+        assert_eq!(
+            names(&sorted),
+            ["docs", "hello-world", "linguist", "Spoon-Knife"]
+        );
+    }
+
+    #[test]
+    fn reverses_sorted_or_fetched_order() {
+        let sorted = query(&["--sort", "stars", "--reverse"]).apply(repositories());
+        assert_eq!(
+            names(&sorted),
+            ["linguist", "Spoon-Knife", "hello-world", "docs"]
+        );
+        let reversed = query(&["--reverse"]).apply(repositories());
+        assert_eq!(
+            names(&reversed),
+            ["docs", "linguist", "Spoon-Knife", "hello-world"]
+        );
+    }
+
+    #[test]
+    fn limits_after_filtering_and_sorting() {
+        let limited =
+            query(&["--only-forks", "--sort", "stars", "--limit", "1"]).apply(repositories());
+        assert_eq!(names(&limited), ["Spoon-Knife"]);
+        assert!(query(&["--limit", "0"]).apply(repositories()).is_empty());
+        assert_eq!(query(&["--limit", "10"]).apply(repositories()).len(), 4);
+    }
+
+    #[test]
+    fn reaches_limit_of_stream() {
+        let limited = query(&["--limit", "2"]);
+        assert!(limited.is_streamable());
+        assert!(!limited.is_limit_reached(1));
+        assert!(limited.is_limit_reached(2));
+        assert!(limited.is_limit_reached(3));
+        assert!(!query(&[]).is_limit_reached(1000));
+        assert!(!query(&["--sort", "name"]).is_streamable());
+        assert!(!query(&["--reverse"]).is_streamable());
+    }
+}
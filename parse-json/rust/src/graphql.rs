@@ -0,0 +1,188 @@
+use crate::account::Account;
+use crate::warn_max_pages;
+use chrono::{DateTime, Utc};
+use rfnd_github::{
+    Connection, Error, GithubClient, License, Owner, OwnerKind, PageInfo, Repository, Visibility,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::ops::ControlFlow;
+
+/// Asks for everything `Repository` needs at once, of another account or of the authenticated
+/// user (the `viewer`).
+const QUERY: &str = include_str!("repositories.graphql");
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Data {
+    repository_owner: Option<RepositoryOwner>,
+    viewer: Option<RepositoryOwner>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RepositoryOwner {
+    repositories: Connection<Node>,
+}
+
+impl Data {
+    // only one of both is selected, see `repositories.graphql`
+    fn into_repositories(self) -> Option<Connection<Node>> {
+        let owner = self.repository_owner.or(self.viewer)?;
+        Some(owner.repositories)
+    }
+
+    fn page_info(&self) -> Option<PageInfo> {
+        let owner = self.repository_owner.as_ref().or(self.viewer.as_ref())?;
+        Some(owner.repositories.page_info.clone())
+    }
+}
+
+/// A repository as the GraphQL API describes it.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Node {
+    database_id: u64,
+    name: String,
+    name_with_owner: String,
+    url: String,
+    description: Option<String>,
+    is_fork: bool,
+    owner: NodeOwner,
+    license_info: Option<NodeLicense>,
+    repository_topics: Topics,
+    stargazer_count: u64,
+    primary_language: Option<Named>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    pushed_at: Option<DateTime<Utc>>,
+    is_archived: bool,
+    visibility: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NodeOwner {
+    #[serde(rename = "__typename")]
+    kind: OwnerKind,
+    database_id: u64,
+    login: String,
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NodeLicense {
+    key: String,
+    name: String,
+    spdx_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Topics {
+    nodes: Vec<Topic>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Topic {
+    topic: Named,
+}
+
+#[derive(Deserialize, Debug)]
+struct Named {
+    name: String,
+}
+
+impl From<Node> for Repository {
+    fn from(node: Node) -> Self {
+        Repository {
+            id: node.database_id,
+            name: node.name,
+            full_name: node.name_with_owner,
+            html_url: node.url,
+            description: node.description,
+            fork: node.is_fork,
+            owner: Owner {
+                login: node.owner.login,
+                id: node.owner.database_id,
+                html_url: node.owner.url,
+                kind: node.owner.kind,
+                extra: Map::new(),
+            },
+            license: node.license_info.map(|license| License {
+                key: license.key,
+                name: license.name,
+                spdx_id: license.spdx_id,
+                extra: Map::new(),
+            }),
+            topics: node
+                .repository_topics
+                .nodes
+                .into_iter()
+                .map(|topic| topic.topic.name)
+                .collect(),
+            stargazers_count: node.stargazer_count,
+            language: node.primary_language.map(|language| language.name),
+            created_at: node.created_at,
+            updated_at: node.updated_at,
+            pushed_at: node.pushed_at,
+            archived: node.is_archived,
+            visibility: match node.visibility.as_str() {
+                "PRIVATE" => Visibility::Private,
+                "INTERNAL" => Visibility::Internal,
+                _ => Visibility::Public,
+            },
+            extra: Map::new(),
+        }
+    }
+}
+
+/// Like `get`, but with the GraphQL API, which follows the cursor of every page instead of a
+/// `Link` header.
+pub async fn get<F>(
+    client: &GithubClient,
+    account: &Account,
+    per_page: u32,
+    max_pages: u32,
+    verbose: bool,
+    mut on_repository: F,
+) -> Result<(), Error>
+where
+    F: FnMut(Repository) -> ControlFlow<()>,
+{
+    let mut variables = Map::new();
+    variables.insert("login".into(), account.login().unwrap_or_default().into());
+    variables.insert("own".into(), (*account == Account::Own).into());
+    // the REST API lists private repositories for the authenticated user only, too
+    let privacy = match account {
+        Account::Own => Value::Null,
+        _ => "PUBLIC".into(),
+    };
+    variables.insert("privacy".into(), privacy);
+    variables.insert("first".into(), per_page.into());
+
+    let mut page = 0;
+    client
+        .graphql_pages(QUERY, variables, Data::page_info, |data: Data| {
+            page += 1;
+            let has_next_page = data.page_info().is_some_and(|info| info.has_next_page);
+            let nodes = data
+                .into_repositories()
+                .map(|repositories| repositories.nodes)
+                .unwrap_or_default();
+            if verbose {
+                eprintln!("Page {} of {}: {} repositories", page, account, nodes.len());
+            }
+
+            for node in nodes {
+                on_repository(node.into())?;
+            }
+            if page >= max_pages && has_next_page {
+                warn_max_pages(account, page);
+            }
+            match page < max_pages {
+                true => ControlFlow::Continue(()),
+                false => ControlFlow::Break(()),
+            }
+        })
+        .await
+}
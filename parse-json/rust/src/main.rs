@@ -1,49 +1,306 @@
-use hyper::rt::{run, Future, Stream};
-use hyper::{Client, Request};
-use hyper_tls::HttpsConnector;
-use serde::Deserialize;
-use std::str::from_utf8;
-
-#[derive(Deserialize, Debug)]
-struct Repository {
-    name: String,
-    description: Option<String>,
-    fork: bool,
+mod account;
+mod filter;
+mod graphql;
+mod output;
+
+use crate::account::Account;
+use crate::filter::Query;
+use crate::output::{OutputFormat, Tagged};
+use clap::{Parser, Subcommand};
+use futures_util::stream::{self, StreamExt};
+use rfnd_github::cli::{self, check_output};
+use rfnd_github::{
+    parse_base_url, CacheCommand, CacheOptions, Error, FixtureOptions, GithubClient, Repository,
+    RetryPolicy, TimeoutOptions, TlsOptions, Token,
+};
+use std::cell::RefCell;
+use std::future::ready;
+use std::io;
+use std::ops::ControlFlow;
+use std::process::exit;
+use tokio::time::sleep;
+
+/// Fetches and prints all repositories of GitHub users and organizations.
+#[derive(Parser, Debug)]
+#[command(name = "parse-json", version)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The accounts whose repositories are listed, given as `<user>` or `org:<organization>`
+    #[arg(
+        value_name = "ACCOUNT",
+        default_value = "donaldpipowitch",
+        value_parser = Account::parse
+    )]
+    accounts: Vec<Account>,
+
+    /// Base URL of the API, e.g. of a GitHub Enterprise instance or a local mock
+    #[arg(long, default_value = "https://api.github.com", value_parser = parse_base_url)]
+    base_url: String,
+
+    /// How many accounts are fetched at the same time
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    concurrency: u32,
+
+    /// How the repositories are printed
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    #[command(flatten)]
+    query: Query,
+
+    /// How many repositories are fetched per request (GitHub allows at most 100)
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..=100))]
+    per_page: u32,
+
+    /// How many pages are fetched at most
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    max_pages: u32,
+
+    /// How often a request is sent at most when it fails with a temporary error
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// List the repositories of the authenticated user instead, including private ones
+    #[arg(long, conflicts_with = "accounts")]
+    own: bool,
+
+    /// Use the GraphQL API instead of the REST API, which needs an access token
+    #[arg(long)]
+    graphql: bool,
+
+    /// Read the access token from this file instead of `GITHUB_TOKEN` or `~/.netrc`
+    #[arg(long, value_name = "FILE", value_parser = Token::from_file)]
+    token_file: Option<Token>,
+
+    /// Sleep until GitHub's rate limit resets instead of failing when it is exhausted
+    #[arg(long)]
+    wait_for_rate_limit: bool,
+
+    /// Print additional information like the remaining rate limit to stderr
+    #[arg(short, long)]
+    verbose: bool,
+
+    #[command(flatten)]
+    tls: TlsOptions,
+
+    #[command(flatten)]
+    timeouts: TimeoutOptions,
+
+    #[command(flatten)]
+    cache: CacheOptions,
+
+    #[command(flatten)]
+    fixtures: FixtureOptions,
 }
 
-fn main() {
-    run(get());
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect or clear the response cache
+    #[command(subcommand)]
+    Cache(CacheCommand),
 }
 
-fn get() -> impl Future<Item = (), Error = ()> {
-    // 4 is number of blocking DNS threads
-    let https = HttpsConnector::new(4).unwrap();
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
 
-    let client = Client::builder().build(https);
+    if let Some(Command::Cache(command)) = args.command {
+        cli::run_cache_command(&args.cache, command);
+    }
 
-    let req = Request::get("https://api.github.com/users/donaldpipowitch/repos")
-        .header("User-Agent", "Mercateo/rust-for-node-developers")
-        .body(hyper::Body::empty())
-        .unwrap();
+    let token = Token::resolve(args.token_file.clone(), &args.base_url).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(2);
+    });
+    if args.own && token.is_none() {
+        eprintln!("--own needs an access token, see --help");
+        exit(2);
+    }
+    if args.graphql && token.is_none() {
+        eprintln!("--graphql needs an access token, see --help");
+        exit(2);
+    }
 
-    client
-        .request(req)
-        .and_then(|res| {
-            let status = res.status();
+    let deadline = args.timeouts.deadline();
+    let client = GithubClient::builder()
+        .base_url(&args.base_url)
+        .token(token)
+        .retry(RetryPolicy {
+            wait_for_rate_limit: args.wait_for_rate_limit,
+            ..RetryPolicy::new(args.max_attempts)
+        })
+        .cache(args.cache.cache())
+        .fixtures(args.fixtures.fixtures())
+        .tls(args.tls.clone())
+        .timeouts(args.timeouts.clone())
+        .build()
+        .unwrap_or_else(|err| fail(err));
 
-            if status.is_client_error() {
-                panic!("Got client error: {}", status.as_u16());
-            }
-            if status.is_server_error() {
-                panic!("Got server error: {}", status.as_u16());
+    // print every repository as soon as it is parsed, if the output format and the query allow
+    // it - otherwise all repositories are collected first
+    let mut stream = match args.query.is_streamable() {
+        true => check_output(output::Stream::new(io::stdout(), args.output)),
+        false => None,
+    };
+    let mut repositories = Vec::new();
+    let mut shown = 0;
+
+    let on_repository = RefCell::new(|tagged: Tagged| {
+        let stream = match &mut stream {
+            Some(stream) => stream,
+            None => {
+                repositories.push(tagged);
+                return ControlFlow::Continue(());
             }
+        };
+
+        if args.query.matches(&tagged.repository) && !args.query.is_limit_reached(shown) {
+            check_output(stream.write(&tagged));
+            shown += 1;
+        }
 
-            let buf = res.into_body().concat2().wait().unwrap();
-            let json = from_utf8(&buf).unwrap();
-            let repositories: Vec<Repository> = serde_json::from_str(&json).unwrap();
-            println!("Result is:\n{:#?}", repositories);
+        match args.query.is_limit_reached(shown) {
+            true => ControlFlow::Break(()),
+            false => ControlFlow::Continue(()),
+        }
+    });
 
-            Ok(())
+    let accounts = match args.own {
+        true => vec![Account::Own],
+        false => args.accounts.clone(),
+    };
+    // all accounts share the client and with it the connection pool - they run concurrently on
+    // this task, so `on_repository` is never borrowed twice at the same time
+    let (client, args, on_repository) = (&client, &args, &on_repository);
+    let mut failures: Vec<(usize, &Account, Error)> = stream::iter(accounts.iter().enumerate())
+        .map(|(position, account)| async move {
+            let on_repository = |repository: Repository| {
+                let account = match account.login() {
+                    Some(login) => login.to_string(),
+                    None => repository.owner.login.clone(),
+                };
+                (on_repository.borrow_mut())(Tagged {
+                    repository,
+                    account,
+                })
+            };
+            let result = match args.graphql {
+                true => {
+                    let get = graphql::get(
+                        client,
+                        account,
+                        args.per_page,
+                        args.max_pages,
+                        args.verbose,
+                        on_repository,
+                    );
+                    deadline.run(get).await
+                }
+                false => {
+                    deadline
+                        .run(get(client, args, account, on_repository))
+                        .await
+                }
+            };
+            result.err().map(|err| (position, account, err))
         })
-        .map_err(|_err| panic!("Couldn't send request."))
+        .buffer_unordered(args.concurrency as usize)
+        .filter_map(ready)
+        .collect()
+        .await;
+
+    match stream {
+        Some(stream) => check_output(stream.finish()),
+        // the repositories of the other accounts are still worth showing
+        None if failures.len() < accounts.len() => {
+            let repositories = args.query.apply(repositories);
+            let mut stdout = io::stdout().lock();
+            check_output(output::write(&mut stdout, args.output, &repositories));
+        }
+        None => {}
+    }
+
+    // the accounts finish in any order, but are reported in the given one, which also decides
+    // the exit code
+    failures.sort_by_key(|(position, _, _)| *position);
+    for (_, account, err) in &failures {
+        report(account, &accounts, err);
+    }
+    if let Some((_, _, err)) = failures.first() {
+        exit(err.exit_code());
+    }
+}
+
+// the account is only named if there is more than one
+fn report(account: &Account, accounts: &[Account], err: &Error) {
+    match accounts.len() {
+        1 => eprintln!("{}", err),
+        _ => eprintln!("Couldn't fetch the repositories of {}: {}", account, err),
+    }
+}
+
+fn fail(err: Error) -> ! {
+    eprintln!("{}", err);
+    exit(err.exit_code());
+}
+
+/// Passes every repository to `on_repository` as soon as it is parsed, until it returns `Break`.
+async fn get<F>(
+    client: &GithubClient,
+    args: &Args,
+    account: &Account,
+    mut on_repository: F,
+) -> Result<(), Error>
+where
+    F: FnMut(Repository) -> ControlFlow<()>,
+{
+    let mut url = account.path(args.per_page);
+
+    // keep following `rel="next"` until there is none or we hit `max_pages`
+    for page in 1.. {
+        let next_page = client.page(&url, &mut on_repository).await?;
+
+        if args.verbose {
+            if next_page.from_cache {
+                eprintln!("Page {} of {} was served from the cache", page, account);
+            }
+            eprintln!("Page {} of {}: {}", page, account, next_page.body_size);
+            if let Some(rate_limit) = next_page.rate_limit {
+                eprintln!("Rate limit: {}", rate_limit);
+            }
+        }
+
+        url = match next_page.next_url {
+            Some(_) if next_page.is_stopped => break,
+            Some(next_url) if page < args.max_pages => next_url,
+            Some(_) => {
+                warn_max_pages(account, page);
+                break;
+            }
+            None => break,
+        };
+
+        // no need to send a request which we know will fail
+        if let Some(rate_limit) = next_page.rate_limit {
+            if args.wait_for_rate_limit && rate_limit.is_exhausted() {
+                eprintln!(
+                    "Rate limit exhausted, waiting {}s for it to reset",
+                    rate_limit.reset_in().as_secs()
+                );
+                sleep(rate_limit.reset_in()).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The listing is incomplete, which shouldn't go unnoticed.
+fn warn_max_pages(account: &Account, pages: u32) {
+    eprintln!(
+        "Stopped after {} pages of {}, more are available; raise --max-pages to get them",
+        pages, account
+    );
 }
@@ -0,0 +1,330 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use rfnd_github::Repository;
+use serde::Serialize;
+use std::io::{self, Write};
+
+// longer descriptions are cut in the table
+const MAX_DESCRIPTION_WIDTH: usize = 60;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned columns for humans
+    Table,
+    /// A pretty printed JSON array with all fields
+    Json,
+    /// One JSON object per line, e.g. for `jq`
+    Ndjson,
+    /// Comma separated values with a header row
+    Csv,
+    /// Rust's `Debug` output
+    Debug,
+}
+
+/// A repository and the account it was listed for, which isn't part of GitHub's response, but
+/// tells the repositories of several accounts apart.
+#[derive(Serialize, Debug)]
+pub struct Tagged {
+    #[serde(flatten)]
+    pub repository: Repository,
+    pub account: String,
+}
+
+pub fn write(
+    out: &mut impl Write,
+    format: OutputFormat,
+    repositories: &[Tagged],
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => write_table(out, repositories),
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, repositories)?;
+            writeln!(out)
+        }
+        OutputFormat::Ndjson | OutputFormat::Csv => {
+            let mut stream = Stream::new(out, format)?.unwrap();
+            for repository in repositories {
+                stream.write(repository)?;
+            }
+            stream.finish()
+        }
+        OutputFormat::Debug => writeln!(out, "Result is:\n{:#?}", repositories),
+    }
+}
+
+/// Writes one repository after another for the formats which don't need to know all
+/// repositories upfront.
+pub enum Stream<W: Write> {
+    Ndjson(W),
+    Csv(Box<csv::Writer<W>>),
+}
+
+impl<W: Write> Stream<W> {
+    /// Returns `None` if the format can't be streamed.
+    pub fn new(out: W, format: OutputFormat) -> io::Result<Option<Self>> {
+        match format {
+            OutputFormat::Ndjson => Ok(Some(Stream::Ndjson(out))),
+            OutputFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(out);
+                writer.write_record(CSV_HEADER)?;
+                Ok(Some(Stream::Csv(Box::new(writer))))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn write(&mut self, repository: &Tagged) -> io::Result<()> {
+        match self {
+            Stream::Ndjson(out) => {
+                serde_json::to_writer(&mut *out, repository)?;
+                writeln!(out)
+            }
+            Stream::Csv(writer) => Ok(writer.write_record(csv_record(repository))?),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Stream::Ndjson(mut out) => out.flush(),
+            Stream::Csv(mut writer) => writer.flush(),
+        }
+    }
+}
+
+fn write_table(out: &mut impl Write, repositories: &[Tagged]) -> io::Result<()> {
+    // the account is only worth a column if there is more than one
+    let has_accounts = repositories
+        .iter()
+        .any(|repository| repository.account != repositories[0].account);
+
+    let mut header = vec!["NAME", "STARS", "LANGUAGE", "FORK", "DESCRIPTION"];
+    if has_accounts {
+        header.insert(0, "ACCOUNT");
+    }
+    let rows: Vec<Vec<String>> = repositories
+        .iter()
+        .map(
+            |Tagged {
+                 repository,
+                 account,
+             }| {
+                let mut row = vec![
+                    repository.name.clone(),
+                    repository.stargazers_count.to_string(),
+                    repository.language.clone().unwrap_or_default(),
+                    if repository.fork { "yes" } else { "" }.to_string(),
+                    truncate(
+                        repository.description.as_deref().unwrap_or_default(),
+                        MAX_DESCRIPTION_WIDTH,
+                    ),
+                ];
+                if has_accounts {
+                    row.insert(0, account.clone());
+                }
+                row
+            },
+        )
+        .collect();
+
+    let mut widths: Vec<usize> = header.iter().map(|column| column.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    write_row(out, &header, &widths)?;
+    for row in &rows {
+        write_row(out, row, &widths)?;
+    }
+    Ok(())
+}
+
+fn write_row(out: &mut impl Write, row: &[impl AsRef<str>], widths: &[usize]) -> io::Result<()> {
+    let cells: Vec<String> = row
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:width$}", cell.as_ref(), width = width))
+        .collect();
+    writeln!(out, "{}", cells.join("  ").trim_end())
+}
+
+fn truncate(value: &str, max_width: usize) -> String {
+    // descriptions sometimes contain line breaks, which would break the table
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if value.chars().count() <= max_width {
+        value
+    } else {
+        let cut: String = value.chars().take(max_width - 1).collect();
+        format!("{}…", cut.trim_end())
+    }
+}
+
+const CSV_HEADER: [&str; 17] = [
+    "id",
+    "name",
+    "full_name",
+    "html_url",
+    "description",
+    "fork",
+    "owner",
+    "license",
+    "topics",
+    "stargazers_count",
+    "language",
+    "created_at",
+    "updated_at",
+    "pushed_at",
+    "archived",
+    "visibility",
+    "account",
+];
+
+fn csv_record(
+    Tagged {
+        repository,
+        account,
+    }: &Tagged,
+) -> [String; 17] {
+    [
+        repository.id.to_string(),
+        repository.name.clone(),
+        repository.full_name.clone(),
+        repository.html_url.clone(),
+        repository.description.clone().unwrap_or_default(),
+        repository.fork.to_string(),
+        repository.owner.login.clone(),
+        repository
+            .license
+            .as_ref()
+            .and_then(|license| license.spdx_id.clone())
+            .unwrap_or_default(),
+        repository.topics.join(";"),
+        repository.stargazers_count.to_string(),
+        repository.language.clone().unwrap_or_default(),
+        timestamp(repository.created_at),
+        timestamp(repository.updated_at),
+        repository.pushed_at.map(timestamp).unwrap_or_default(),
+        repository.archived.to_string(),
+        repository.visibility.as_str().to_string(),
+        account.clone(),
+    ]
+}
+
+// the same format GitHub uses, e.g. `2019-01-31T12:00:00Z`
+fn timestamp(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tagged(name: &str, account: &str) -> Tagged {
+        let repository = json!({
+            "id": 1296269,
+            "name": name,
+            "full_name": format!("octocat/{}", name),
+            "html_url": format!("https://github.com/octocat/{}", name),
+            "description": "My first repository",
+            "fork": false,
+            "owner": {
+                "login": "octocat",
+                "id": 1,
+                "html_url": "https://github.com/octocat",
+                "type": "User"
+            },
+            "license": { "key": "mit", "name": "MIT License", "spdx_id": "MIT" },
+            "topics": ["api", "octocat"],
+            "stargazers_count": 42,
+            "language": "Rust",
+            "created_at": "2011-01-26T19:01:12Z",
+            "updated_at": "2011-01-26T19:14:43Z",
+            "pushed_at": null,
+            "archived": false,
+            "visibility": "public"
+        });
+        Tagged {
+            repository: serde_json::from_value(repository).unwrap(),
+            account: account.to_string(),
+        }
+    }
+
+    fn written(format: OutputFormat, repositories: &[Tagged]) -> String {
+        let mut out = Vec::new();
+        write(&mut out, format, repositories).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn aligns_table_columns() {
+        let mut linguist = tagged("linguist", "octocat");
+        linguist.repository.stargazers_count = 0;
+        linguist.repository.language = None;
+        linguist.repository.fork = true;
+        linguist.repository.description = None;
+
+        assert_eq!(
+            written(
+                OutputFormat::Table,
+                &[tagged("hello-world", "octocat"), linguist]
+            ),
+            "NAME         STARS  LANGUAGE  FORK  DESCRIPTION\n\
+             hello-world  42     Rust            My first repository\n\
+             linguist     0                yes\n"
+        );
+    }
+
+    #[test]
+    fn shows_accounts_in_table_if_there_are_several() {
+        let table = written(
+            OutputFormat::Table,
+            &[tagged("hello-world", "octocat"), tagged("docs", "github")],
+        );
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("ACCOUNT  NAME "), "{}", table);
+        assert!(lines[1].starts_with("octocat  hello-world "), "{}", table);
+        assert!(lines[2].starts_with("github   docs "), "{}", table);
+    }
+
+    #[test]
+    fn truncates_by_characters() {
+        assert_eq!(truncate("héllo wörld", 11), "héllo wörld");
+        assert_eq!(truncate("héllo wörld ☃☃☃", 10), "héllo wör…");
+        // the cut doesn't leave a space in front of the ellipsis
+        assert_eq!(truncate("hello world", 7), "hello…");
+    }
+
+    #[test]
+    fn collapses_line_breaks() {
+        assert_eq!(
+            truncate("first line\r\nsecond\n\n  third", 60),
+            "first line second third"
+        );
+        assert_eq!(truncate("  \n", 60), "");
+    }
+
+    #[test]
+    fn writes_csv_columns_in_header_order() {
+        let csv = written(OutputFormat::Csv, &[tagged("hello-world", "octocat")]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER.join(","));
+        assert_eq!(
+            lines[1],
+            "1296269,hello-world,octocat/hello-world,https://github.com/octocat/hello-world,\
+             My first repository,false,octocat,MIT,api;octocat,42,Rust,2011-01-26T19:01:12Z,\
+             2011-01-26T19:14:43Z,,false,public,octocat"
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn quotes_csv_fields_with_commas_and_quotes() {
+        let mut repository = tagged("hello-world", "octocat");
+        repository.repository.description = Some(r#"Say "hi", then bye"#.to_string());
+        let csv = written(OutputFormat::Csv, &[repository]);
+        assert!(csv.contains(r#","Say ""hi"", then bye","#), "{}", csv);
+    }
+}
@@ -0,0 +1,322 @@
+use rfnd_github_mock::{stderr, stdout, Example, MockGithub};
+use serde_json::Value;
+use std::process::Output;
+
+const EXAMPLE: Example = Example::new(
+    env!("CARGO_BIN_EXE_parse-json"),
+    env!("CARGO_TARGET_TMPDIR"),
+);
+
+// the `name` of every repository in the JSON output
+fn names(output: &Output) -> Vec<String> {
+    let repositories: Vec<Value> = serde_json::from_slice(&output.stdout).unwrap();
+    repositories
+        .iter()
+        .map(|repository| repository["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn follows_all_pages() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["--per-page", "2", "-o", "json", "octocat"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(names(&output), ["hello-world", "spoon-knife", "linguist"]);
+    assert!(!stderr(&output).contains("Stopped after"));
+
+    let requests = server.requests();
+    let paths: Vec<&str> = requests.iter().map(|req| req.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "/users/octocat/repos?per_page=2",
+            "/users/octocat/repos?per_page=2&page=2"
+        ]
+    );
+    // the connection is kept open for the next page
+    assert_eq!(requests[0].connection, requests[1].connection);
+}
+
+#[test]
+fn stops_after_max_pages() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--per-page",
+            "1",
+            "--max-pages",
+            "2",
+            "-o",
+            "json",
+            "octocat",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(names(&output), ["hello-world", "spoon-knife"]);
+    assert_eq!(server.requests().len(), 2);
+    assert!(
+        stderr(&output).contains("Stopped after 2 pages of octocat, more are available"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn lists_organizations() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["-o", "ndjson", "org:github"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).lines().count(), 2);
+    assert_eq!(server.requests()[0].path, "/orgs/github/repos?per_page=100");
+}
+
+#[test]
+fn lists_own_repositories_with_token() {
+    let server = MockGithub::start();
+    let token = EXAMPLE.token_file("lists_own_repositories_with_token");
+    let output = EXAMPLE.run(&server, &["--token-file", &token, "--own", "-o", "json"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(names(&output).len(), 3);
+    let requests = server.requests();
+    assert!(requests[0].path.starts_with("/user/repos?"));
+    assert_eq!(requests[0].header("authorization"), Some("Bearer secret"));
+}
+
+#[test]
+fn fails_for_missing_user() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["missing"]);
+
+    assert_eq!(output.status.code(), Some(6));
+    assert!(stderr(&output).contains("404"), "{}", stderr(&output));
+}
+
+#[test]
+fn shows_other_accounts_if_one_fails() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["-o", "json", "octocat", "missing"]);
+
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(names(&output).len(), 3);
+    assert!(
+        stderr(&output).contains("Couldn't fetch the repositories of missing"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn reports_failures_in_given_order() {
+    let server = MockGithub::start();
+    // `unavailable` is retried, so it fails after `missing`
+    let output = EXAMPLE.run(&server, &["--max-attempts", "2", "unavailable", "missing"]);
+
+    assert_eq!(output.status.code(), Some(7));
+    let stderr = stderr(&output);
+    let unavailable = stderr.find("repositories of unavailable").unwrap();
+    let missing = stderr.find("repositories of missing").unwrap();
+    assert!(unavailable < missing, "{}", stderr);
+}
+
+#[test]
+fn fails_for_server_error() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["broken"]);
+
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn retries_unavailable_server() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["--max-attempts", "2", "unavailable"]);
+
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn fails_for_malformed_json() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["malformed"]);
+
+    assert_eq!(output.status.code(), Some(9));
+    assert!(stderr(&output).contains("line 1"), "{}", stderr(&output));
+}
+
+#[test]
+fn fails_for_exhausted_rate_limit() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["limited"]);
+
+    assert_eq!(output.status.code(), Some(10));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn lists_same_repositories_with_graphql() {
+    let server = MockGithub::start();
+    let token = EXAMPLE.token_file("lists_same_repositories_with_graphql");
+    // both accounts are fetched at once, so their repositories would come in any order
+    let rest = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &token,
+            "--sort",
+            "name",
+            "octocat",
+            "org:github",
+        ],
+    );
+    let graphql = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &token,
+            "--graphql",
+            "--sort",
+            "name",
+            "octocat",
+            "org:github",
+        ],
+    );
+
+    assert!(graphql.status.success(), "{}", stderr(&graphql));
+    assert_eq!(stdout(&graphql), stdout(&rest));
+    let requests = server.requests();
+    let graphql_requests: Vec<_> = requests
+        .iter()
+        .filter(|req| req.path == "/graphql")
+        .collect();
+    assert_eq!(graphql_requests.len(), 2);
+    assert_eq!(graphql_requests[0].method, "POST");
+}
+
+#[test]
+fn replays_graphql_queries_of_every_account() {
+    let server = MockGithub::start();
+    let dir = EXAMPLE.tmp_path("graphql-fixtures");
+    let token = EXAMPLE.token_file("replays_graphql_queries_of_every_account");
+    let args = [
+        "--token-file",
+        &token,
+        "--graphql",
+        "--sort",
+        "name",
+        "-o",
+        "json",
+        "octocat",
+        "org:github",
+    ];
+
+    let recorded = EXAMPLE.run(&server, &[&["--record", &dir], &args[..]].concat());
+    assert!(recorded.status.success(), "{}", stderr(&recorded));
+    let replayed = EXAMPLE.run(&server, &[&["--replay", &dir], &args[..]].concat());
+    assert!(replayed.status.success(), "{}", stderr(&replayed));
+
+    // every account is answered with its own recording, although all queries are POSTs to the
+    // same path
+    assert_eq!(names(&replayed), names(&recorded));
+    assert_eq!(
+        names(&replayed),
+        [
+            "docs",
+            "gitignore",
+            "hello-world",
+            "linguist",
+            "spoon-knife"
+        ]
+    );
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn follows_graphql_cursors() {
+    let server = MockGithub::start();
+    let token = EXAMPLE.token_file("follows_graphql_cursors");
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &token,
+            "--graphql",
+            "--per-page",
+            "2",
+            "-o",
+            "json",
+            "octocat",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(names(&output), ["hello-world", "spoon-knife", "linguist"]);
+
+    let cursors: Vec<Value> = server
+        .requests()
+        .iter()
+        .map(|req| {
+            let body: Value = serde_json::from_slice(&req.body).unwrap();
+            body["variables"]["cursor"].clone()
+        })
+        .collect();
+    assert_eq!(cursors, [Value::Null, Value::from("cursor:2")]);
+}
+
+#[test]
+fn stops_graphql_after_max_pages() {
+    let server = MockGithub::start();
+    let token = EXAMPLE.token_file("stops_graphql_after_max_pages");
+    let output = EXAMPLE.run(
+        &server,
+        &[
+            "--token-file",
+            &token,
+            "--graphql",
+            "--per-page",
+            "1",
+            "--max-pages",
+            "2",
+            "-o",
+            "json",
+            "octocat",
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(names(&output), ["hello-world", "spoon-knife"]);
+    assert!(
+        stderr(&output).contains("Stopped after 2 pages of octocat, more are available"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn fails_for_graphql_errors() {
+    let server = MockGithub::start();
+    let token = EXAMPLE.token_file("fails_for_graphql_errors");
+    let output = EXAMPLE.run(&server, &["--token-file", &token, "--graphql", "missing"]);
+
+    assert_eq!(output.status.code(), Some(15));
+    assert!(
+        stderr(&output).contains("Could not resolve to a RepositoryOwner with the login of 'missing'. (at repositoryOwner)"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn needs_token_for_graphql() {
+    let server = MockGithub::start();
+    let output = EXAMPLE.run(&server, &["--graphql", "octocat"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(server.requests().is_empty());
+}
@@ -0,0 +1,878 @@
+//! A local stand-in for the GitHub API, so the examples can be tested without the internet.
+//!
+//! The server listens on an ephemeral port of `127.0.0.1` and answers with canned responses.
+//! Which response is sent depends on the account in the path:
+//!
+//! - `octocat` (a user) and `github` (an organization) exist and have a few repositories,
+//!   which are paginated according to `per_page` and `page`
+//! - `missing` doesn't exist (`404`)
+//! - `broken` fails with `500`
+//! - `unavailable` fails with `503` and asks to be retried right away
+//! - `malformed` answers with JSON which ends too early
+//! - `limited` fails with `403`, because the rate limit is exhausted
+//! - `throttled` fails with `429` and an exhausted rate limit, but without `Retry-After`
+//! - `depleted` doesn't exist either, but its `404` used up the rate limit
+//! - `stalled` doesn't answer for a few seconds, so the client runs into its timeouts
+//!
+//! `/user/repos` lists the repositories of `octocat`, but only with an `Authorization` header.
+//! With one, issues can be created with `POST /repos/OWNER/REPO/issues` and repositories deleted
+//! with `DELETE /repos/OWNER/REPO`, which doesn't change what the server answers afterwards.
+//! Like GitHub, `/repos/OWNER/REPO/tarball` redirects (`302`) to another host, which is
+//! `localhost` instead of `127.0.0.1` here. There `/codeload/OWNER/REPO/legacy.tar.gz` answers
+//! with `archive()` without needing a token, and supports `Range` requests.
+//!
+//! `octocat/moved` was renamed to `octocat/hello-world`, so everything below `/repos/octocat/moved`
+//! redirects there, with `301` for `GET` and `307` otherwise, while `/repos/octocat/circular`
+//! redirects to itself.
+//!
+//! `POST /graphql` needs a token, too. It doesn't parse the query, but lists the repositories of
+//! the `viewer` (`octocat`) if the variable `$own` is true and those of the `repositoryOwner`
+//! named by `$login` otherwise, `$first` at a time after `$cursor`. Like GitHub, it ignores
+//! `$cursor` if the query doesn't declare it. Every repository has all
+//! fields of the GraphQL schema which `parse-json` needs.
+//!
+//! Successful responses are compressed with gzip if the client accepts it. They have an `ETag`,
+//! so a request with a matching `If-None-Match` gets a `304 Not Modified`. Connections are kept
+//! open for further requests until the client closes them.
+//!
+//! `MockProxy` is a stand-in for an HTTP proxy, which opens a tunnel for every `CONNECT`.
+//! `Example` runs the binaries of the examples against the server in their integration tests.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// how long `stalled` takes to answer, longer than any timeout the tests use
+const STALL: Duration = Duration::from_secs(5);
+
+/// A running mock server. It stops when the process exits.
+#[derive(Debug)]
+pub struct MockGithub {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Recorded>>>,
+}
+
+/// A request the server received.
+#[derive(Debug, Clone)]
+pub struct Recorded {
+    pub method: String,
+    /// The path including the query.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// The connection the request was sent on, counted from 0 in the order they were opened.
+    pub connection: usize,
+}
+
+impl Recorded {
+    /// The first header with this name, which is compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl MockGithub {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind mock server");
+        let addr = listener.local_addr().expect("Mock server has no address");
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = Arc::clone(&requests);
+        thread::spawn(move || {
+            for (connection, stream) in listener.incoming().flatten().enumerate() {
+                let received = Arc::clone(&received);
+                thread::spawn(move || {
+                    // the client may go away at any time, which is none of our business
+                    let _ = serve(stream, connection, addr, &received);
+                });
+            }
+        });
+
+        MockGithub { addr, requests }
+    }
+
+    /// The base URL to pass to the client, e.g. `http://127.0.0.1:54321`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// All requests received so far, in the order they arrived.
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// A running proxy, which tunnels every `CONNECT` to wherever it asks for without checking any
+/// credentials. It stops when the process exits.
+#[derive(Debug)]
+pub struct MockProxy {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Recorded>>>,
+}
+
+impl MockProxy {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind mock proxy");
+        let addr = listener.local_addr().expect("Mock proxy has no address");
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = Arc::clone(&requests);
+        thread::spawn(move || {
+            for (connection, stream) in listener.incoming().flatten().enumerate() {
+                let received = Arc::clone(&received);
+                thread::spawn(move || {
+                    let _ = tunnel(stream, connection, &received);
+                });
+            }
+        });
+
+        MockProxy { addr, requests }
+    }
+
+    /// The address to use as `HTTP_PROXY`, e.g. `127.0.0.1:54321`.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// All `CONNECT` requests received so far, whose `path` is where the tunnel leads to.
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// The body of `/repos/OWNER/REPO/tarball`: a few hundred KiB of bytes, which aren't valid UTF-8.
+pub fn archive() -> Vec<u8> {
+    (0..300 * 1024).map(|index| (index % 251) as u8).collect()
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, body: Value) -> Self {
+        Response {
+            status,
+            headers: rate_limit(59),
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    fn message(status: u16, message: &str) -> Self {
+        Response::json(
+            status,
+            json!({ "message": message, "documentation_url": "https://docs.github.com/rest" }),
+        )
+    }
+}
+
+/// Runs the binary of an example against `MockGithub`, isolated from the environment of the
+/// test, e.g. `Example::new(env!("CARGO_BIN_EXE_parse-json"), env!("CARGO_TARGET_TMPDIR"))`.
+#[derive(Debug, Clone, Copy)]
+pub struct Example {
+    binary: &'static str,
+    tmp_dir: &'static str,
+}
+
+impl Example {
+    pub const fn new(binary: &'static str, tmp_dir: &'static str) -> Self {
+        Example { binary, tmp_dir }
+    }
+
+    /// Runs the binary without a cache, a token or a proxy from the environment.
+    pub fn command(&self, server: &MockGithub, args: &[&str]) -> Command {
+        self.cached_command(server, None, args)
+    }
+
+    /// Like `command`, but with a cache in `cache_dir`.
+    pub fn cached_command(
+        &self,
+        server: &MockGithub,
+        cache_dir: Option<&str>,
+        args: &[&str],
+    ) -> Command {
+        self.command_at(&server.url(), cache_dir, args)
+    }
+
+    /// Like `cached_command`, but for any base URL, e.g. one nobody listens at.
+    pub fn command_at(&self, base_url: &str, cache_dir: Option<&str>, args: &[&str]) -> Command {
+        let mut command = Command::new(self.binary);
+        command.args(["--base-url", base_url]);
+        match cache_dir {
+            Some(dir) => command.args(["--cache-dir", dir]),
+            None => command.arg("--no-cache"),
+        };
+        command.args(args).env_clear().env("HOME", self.tmp_dir);
+        command
+    }
+
+    pub fn run(&self, server: &MockGithub, args: &[&str]) -> Output {
+        self.command(server, args)
+            .output()
+            .unwrap_or_else(|err| panic!("Couldn't run {}: {}", self.binary, err))
+    }
+
+    /// A path below the temporary directory of the tests, which is removed if it exists.
+    /// Every test needs paths of its own, because the tests run in parallel.
+    pub fn tmp_path(&self, name: &str) -> String {
+        let path = format!("{}/{}", self.tmp_dir, name);
+        let _ = std::fs::remove_dir_all(&path);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// A file with a token, which the mock needs to change anything and for GraphQL.
+    pub fn token_file(&self, test: &str) -> String {
+        let token = self.tmp_path(&format!("{}.token", test));
+        std::fs::write(&token, "secret\n").unwrap();
+        token
+    }
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+// the request line and the headers, the body is left in the reader. `None` if the connection
+// was closed before another request.
+fn read_head(reader: &mut impl BufRead, connection: usize) -> io::Result<Option<Recorded>> {
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    Ok(Some(Recorded {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+        connection,
+    }))
+}
+
+// copies bytes both ways until both sides closed their connection
+fn tunnel(
+    mut stream: TcpStream,
+    connection: usize,
+    requests: &Mutex<Vec<Recorded>>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = match read_head(&mut reader, connection)? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+    let target = request.path.clone();
+    let is_connect = request.method == "CONNECT";
+    requests.lock().unwrap().push(request);
+
+    let mut upstream = match is_connect {
+        true => TcpStream::connect(&target)?,
+        false => {
+            // requests for `http://` URLs are tunnelled as well, so nothing else is supported
+            stream.write_all(b"HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\n\r\n")?;
+            return Ok(());
+        }
+    };
+    stream.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+    upstream.write_all(reader.buffer())?;
+
+    let (mut from_client, mut to_upstream) = (reader.into_inner(), upstream.try_clone()?);
+    thread::spawn(move || {
+        let _ = io::copy(&mut from_client, &mut to_upstream);
+        let _ = to_upstream.shutdown(Shutdown::Write);
+    });
+    io::copy(&mut upstream, &mut stream)?;
+    stream.shutdown(Shutdown::Write)
+}
+
+// answers one request after another, until the client closes the connection or asks to
+fn serve(
+    mut stream: TcpStream,
+    connection: usize,
+    addr: SocketAddr,
+    requests: &Mutex<Vec<Recorded>>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    while let Some(mut request) = read_head(&mut reader, connection)? {
+        if let Some(length) = request.header("content-length") {
+            let length = length.parse().unwrap_or(0);
+            request.body.resize(length, 0);
+            reader.read_exact(&mut request.body)?;
+        } else if request
+            .header("transfer-encoding")
+            .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+        {
+            request.body = read_chunked(&mut reader)?;
+        }
+
+        let is_last = request
+            .header("connection")
+            .is_some_and(|connection| connection.eq_ignore_ascii_case("close"));
+        let (head, body) = response(&request, addr, is_last)?;
+        requests.lock().unwrap().push(request);
+
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&body)?;
+        stream.flush()?;
+        if is_last {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// the status line and headers and the body of the response to the request
+fn response(request: &Recorded, addr: SocketAddr, is_last: bool) -> io::Result<(String, Vec<u8>)> {
+    let mut response = respond(request, addr);
+    if response.status == 200 {
+        // the same body always gets the same tag, even across servers
+        let mut hasher = DefaultHasher::new();
+        response.body.hash(&mut hasher);
+        let etag = format!("\"{:016x}\"", hasher.finish());
+
+        if request.header("if-none-match") == Some(etag.as_str()) {
+            response.status = 304;
+            response.body = Vec::new();
+        }
+        response.headers.push(("ETag", etag));
+        response
+            .headers
+            .push(("Vary", "Accept, Accept-Encoding".to_string()));
+    }
+    let mut body = response.body;
+    let accepts_gzip = request.header("accept-encoding").is_some_and(|encodings| {
+        encodings
+            .split(',')
+            .any(|encoding| encoding.trim() == "gzip")
+    });
+    if response.status == 200 && accepts_gzip {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&body)?;
+        body = encoder.finish()?;
+        response
+            .headers
+            .push(("Content-Encoding", "gzip".to_string()));
+    }
+
+    let mut out = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status,
+        reason(response.status)
+    );
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !response
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+    {
+        out.push_str("Content-Type: application/json; charset=utf-8\r\n");
+    }
+    out.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    if is_last {
+        out.push_str("Connection: close\r\n");
+    }
+    out.push_str("\r\n");
+    Ok((out, body))
+}
+
+fn respond(request: &Recorded, addr: SocketAddr) -> Response {
+    let (path, query) = match request.path.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (request.path.as_str(), ""),
+    };
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    if segments == ["graphql"] && request.method == "POST" {
+        return graphql(request);
+    }
+    if let ["codeload", owner, repo, "legacy.tar.gz"] = segments.as_slice() {
+        let exists = account(owner).is_some_and(|(_, repositories)| {
+            repositories
+                .iter()
+                .any(|repository| repository["name"] == *repo)
+        });
+        return match exists && request.method == "GET" {
+            true => tarball(request),
+            false => Response::message(404, "Not Found"),
+        };
+    }
+    if let ["repos", owner, repo, rest @ ..] = segments.as_slice() {
+        return match (*owner, *repo) {
+            ("octocat", "moved") => {
+                let status = if request.method == "GET" { 301 } else { 307 };
+                let moved = request.path.replacen("/moved", "/hello-world", 1);
+                redirect(status, format!("http://{}{}", addr, moved))
+            }
+            ("octocat", "circular") => redirect(302, format!("http://{}{}", addr, request.path)),
+            _ => repository(request, addr, owner, repo, rest),
+        };
+    }
+
+    let (login, is_listing) = match segments.as_slice() {
+        ["users", login] => (*login, false),
+        ["users", login, "repos"] | ["orgs", login, "repos"] => (*login, true),
+        ["user", "repos"] if request.header("authorization").is_some() => ("octocat", true),
+        ["user", "repos"] => return Response::message(401, "Requires authentication"),
+        _ => return Response::message(404, "Not Found"),
+    };
+
+    match login {
+        "broken" => Response::message(500, "Server Error"),
+        "unavailable" => {
+            let mut response = Response::message(503, "Service Unavailable");
+            response.headers.push(("Retry-After", "0".to_string()));
+            response
+        }
+        "malformed" if is_listing => Response {
+            status: 200,
+            headers: rate_limit(59),
+            body: br#"[{"id": 1, "name": "#.to_vec(),
+        },
+        "malformed" => Response {
+            status: 200,
+            headers: rate_limit(59),
+            body: br#"{"login": "malformed", "id": "#.to_vec(),
+        },
+        "stalled" => {
+            thread::sleep(STALL);
+            Response::message(504, "Gateway Timeout")
+        }
+        "limited" | "throttled" => Response {
+            status: if login == "limited" { 403 } else { 429 },
+            headers: rate_limit(0),
+            body: json!({ "message": "API rate limit exceeded for 127.0.0.1." })
+                .to_string()
+                .into_bytes(),
+        },
+        "depleted" => Response {
+            status: 404,
+            headers: rate_limit(0),
+            body: json!({ "message": "Not Found" }).to_string().into_bytes(),
+        },
+        _ => match account(login) {
+            Some((_, repositories)) if is_listing => page(&repositories, query, path, addr),
+            Some((user, _)) => Response::json(200, user),
+            None => Response::message(404, "Not Found"),
+        },
+    }
+}
+
+// a repository and the endpoints below `/repos/OWNER/REPO` which change something
+fn repository(
+    request: &Recorded,
+    addr: SocketAddr,
+    owner: &str,
+    repo: &str,
+    rest: &[&str],
+) -> Response {
+    let repository = account(owner).and_then(|(_, repositories)| {
+        repositories
+            .into_iter()
+            .find(|repository| repository["name"] == repo)
+    });
+    let repository = match repository {
+        Some(repository) => repository,
+        None => return Response::message(404, "Not Found"),
+    };
+    if request.method != "GET" && request.header("authorization").is_none() {
+        return Response::message(401, "Requires authentication");
+    }
+
+    match (request.method.as_str(), rest) {
+        ("GET", []) => Response::json(200, repository),
+        ("POST", ["issues"]) => create_issue(&request.body, owner, repo),
+        ("DELETE", []) => Response {
+            status: 204,
+            headers: rate_limit(59),
+            body: Vec::new(),
+        },
+        ("GET", ["tarball"]) => redirect(
+            302,
+            format!(
+                "http://localhost:{}/codeload/{}/{}/legacy.tar.gz",
+                addr.port(),
+                owner,
+                repo
+            ),
+        ),
+        _ => Response::message(404, "Not Found"),
+    }
+}
+
+fn graphql(request: &Recorded) -> Response {
+    if request.header("authorization").is_none() {
+        return Response::message(401, "This endpoint requires you to be authenticated.");
+    }
+    let body: Value = match serde_json::from_slice(&request.body) {
+        Ok(body) => body,
+        Err(_) => return Response::message(400, "Problems parsing JSON"),
+    };
+    let variables = &body["variables"];
+
+    let (field, login) = match variables["own"].as_bool() {
+        Some(true) => ("viewer", "octocat"),
+        _ => match variables["login"].as_str() {
+            Some(login) => ("repositoryOwner", login),
+            None => {
+                return Response::json(
+                    200,
+                    json!({ "errors": [{
+                        "message": "Variable $login of type String! was provided invalid value",
+                    }] }),
+                )
+            }
+        },
+    };
+    let repositories = match account(login) {
+        Some((_, repositories)) => repositories,
+        None => {
+            return Response::json(
+                200,
+                json!({
+                    "data": { field: null },
+                    "errors": [{
+                        "type": "NOT_FOUND",
+                        "path": [field],
+                        "message": format!(
+                            "Could not resolve to a RepositoryOwner with the login of '{}'.",
+                            login
+                        ),
+                    }],
+                }),
+            )
+        }
+    };
+
+    // cursors are opaque to the client, ours are just the index of the last node
+    let first = variables["first"].as_u64().unwrap_or(30).max(1) as usize;
+    let declares_cursor = body["query"]
+        .as_str()
+        .is_some_and(|query| query.contains("$cursor"));
+    let start = variables["cursor"]
+        .as_str()
+        .filter(|_| declares_cursor)
+        .and_then(|cursor| cursor.strip_prefix("cursor:"))
+        .and_then(|index| index.parse::<usize>().ok())
+        .unwrap_or(0);
+    let nodes: Vec<Value> = repositories
+        .iter()
+        .skip(start)
+        .take(first)
+        .map(graphql_repository)
+        .collect();
+    let end = start + nodes.len();
+
+    Response::json(
+        200,
+        json!({ "data": { field: {
+            "login": login,
+            "repositories": {
+                "nodes": nodes,
+                "pageInfo": {
+                    "hasNextPage": end < repositories.len(),
+                    "endCursor": (end > start).then(|| format!("cursor:{}", end)),
+                },
+            },
+        } } }),
+    )
+}
+
+// the same repository as the REST API describes it
+fn graphql_repository(repository: &Value) -> Value {
+    let owner = &repository["owner"];
+    json!({
+        "databaseId": repository["id"],
+        "name": repository["name"],
+        "nameWithOwner": repository["full_name"],
+        "url": repository["html_url"],
+        "description": repository["description"],
+        "isFork": repository["fork"],
+        "owner": {
+            "__typename": owner["type"],
+            "databaseId": owner["id"],
+            "login": owner["login"],
+            "url": owner["html_url"],
+        },
+        "licenseInfo": null,
+        "repositoryTopics": { "nodes": [] },
+        "stargazerCount": repository["stargazers_count"],
+        "primaryLanguage": repository["language"].as_str().map(|name| json!({ "name": name })),
+        "createdAt": repository["created_at"],
+        "updatedAt": repository["updated_at"],
+        "pushedAt": repository["pushed_at"],
+        "isArchived": repository["archived"],
+        "visibility": "PUBLIC",
+    })
+}
+
+// supports the only kind of range a client resuming a download needs: `bytes=START-`
+fn tarball(request: &Recorded) -> Response {
+    let archive = archive();
+    let start = request
+        .header("range")
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.strip_suffix('-'))
+        .and_then(|start| start.parse::<usize>().ok());
+
+    let mut headers = rate_limit(59);
+    headers.push(("Content-Type", "application/x-gzip".to_string()));
+    headers.push(("Accept-Ranges", "bytes".to_string()));
+    match start {
+        None => Response {
+            status: 200,
+            headers,
+            body: archive,
+        },
+        Some(start) if start >= archive.len() => {
+            headers.push(("Content-Range", format!("bytes */{}", archive.len())));
+            Response {
+                status: 416,
+                headers,
+                body: Vec::new(),
+            }
+        }
+        Some(start) => {
+            headers.push((
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, archive.len() - 1, archive.len()),
+            ));
+            Response {
+                status: 206,
+                headers,
+                body: archive[start..].to_vec(),
+            }
+        }
+    }
+}
+
+fn create_issue(body: &[u8], owner: &str, repo: &str) -> Response {
+    let issue: Value = match serde_json::from_slice(body) {
+        Ok(issue) => issue,
+        Err(_) => return Response::message(400, "Problems parsing JSON"),
+    };
+    let title = match issue["title"].as_str() {
+        Some(title) => title,
+        None => return Response::message(422, "Validation Failed"),
+    };
+
+    let number = 1347;
+    Response::json(
+        201,
+        json!({
+            "number": number,
+            "title": title,
+            "body": issue["body"],
+            "state": "open",
+            "html_url": format!("https://github.com/{}/{}/issues/{}", owner, repo, number),
+        }),
+    )
+}
+
+// answers with one page of the repositories and links to the next one like GitHub does
+fn page(repositories: &[Value], query: &str, path: &str, addr: SocketAddr) -> Response {
+    let param = |name: &str| -> Option<usize> {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .and_then(|(_, value)| value.parse().ok())
+    };
+    let per_page = param("per_page").unwrap_or(30).max(1);
+    let page = param("page").unwrap_or(1).max(1);
+
+    let items: Vec<Value> = repositories
+        .iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .cloned()
+        .collect();
+    let mut response = Response::json(200, Value::Array(items));
+
+    let last = repositories.len().div_ceil(per_page).max(1);
+    if page < last {
+        let link = |page| {
+            format!(
+                "http://{}{}?per_page={}&page={}",
+                addr, path, per_page, page
+            )
+        };
+        response.headers.push((
+            "Link",
+            format!(
+                "<{}>; rel=\"next\", <{}>; rel=\"last\"",
+                link(page + 1),
+                link(last)
+            ),
+        ));
+    }
+    response
+}
+
+fn account(login: &str) -> Option<(Value, Vec<Value>)> {
+    let (kind, name, repositories) = match login {
+        "octocat" => (
+            "User",
+            "The Octocat",
+            vec![
+                ("hello-world", Some("Rust"), 42),
+                ("spoon-knife", Some("HTML"), 7),
+                ("linguist", None, 0),
+            ],
+        ),
+        "github" => (
+            "Organization",
+            "GitHub",
+            vec![("docs", Some("JavaScript"), 100), ("gitignore", None, 3)],
+        ),
+        _ => return None,
+    };
+
+    let user = json!({
+        "login": login,
+        "id": 1,
+        "html_url": format!("https://github.com/{}", login),
+        "type": kind,
+        "name": name,
+        "company": "@github",
+        "blog": "",
+        "location": "San Francisco",
+        "bio": null,
+        "public_repos": repositories.len(),
+        "followers": 10,
+        "following": 0,
+        "created_at": "2011-01-25T18:44:36Z",
+    });
+    let repositories = repositories
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, language, stars))| {
+            json!({
+                "id": index + 1,
+                "name": name,
+                "full_name": format!("{}/{}", login, name),
+                "html_url": format!("https://github.com/{}/{}", login, name),
+                "description": null,
+                "fork": false,
+                "owner": {
+                    "login": login,
+                    "id": 1,
+                    "html_url": format!("https://github.com/{}", login),
+                    "type": kind,
+                },
+                "license": null,
+                "topics": [],
+                "stargazers_count": stars,
+                "language": language,
+                "created_at": "2011-01-26T19:01:12Z",
+                "updated_at": "2024-05-01T10:00:00Z",
+                "pushed_at": "2024-05-01T10:00:00Z",
+                "archived": false,
+                "visibility": "public",
+            })
+        })
+        .collect();
+
+    Some((user, repositories))
+}
+
+// reads a body sent with `Transfer-Encoding: chunked`, trailers are ignored
+fn read_chunked(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size"))?;
+
+        if size == 0 {
+            // the trailers end with an empty line like the headers do
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        // every chunk is followed by a line break
+        reader.read_line(&mut line)?;
+    }
+}
+
+fn redirect(status: u16, location: String) -> Response {
+    let mut response = Response::message(status, reason(status));
+    response.headers.push(("Location", location));
+    response
+}
+
+fn rate_limit(remaining: u64) -> Vec<(&'static str, String)> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    vec![
+        ("X-RateLimit-Limit", "60".to_string()),
+        ("X-RateLimit-Remaining", remaining.to_string()),
+        ("X-RateLimit-Reset", (now.as_secs() + 3600).to_string()),
+    ]
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        416 => "Range Not Satisfiable",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,147 @@
+use hyper::header::HeaderValue;
+use hyper::Uri;
+use std::env;
+use std::fmt;
+use std::fs;
+
+/// A GitHub access token. It is never printed, not even in `Debug` output.
+#[derive(Clone)]
+pub struct Token(String);
+
+impl Token {
+    pub fn new(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err("the token is empty".to_string());
+        }
+        // don't echo the token in the error message
+        HeaderValue::from_str(value).map_err(|_| "the token contains invalid characters")?;
+        Ok(Token(value.to_string()))
+    }
+
+    /// Reads the token from a file, e.g. one mounted by a secret store.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content =
+            fs::read_to_string(path).map_err(|err| format!("Couldn't read token file: {}", err))?;
+        Token::new(&content)
+    }
+
+    /// Looks for a token in the `GITHUB_TOKEN` environment variable and then in `~/.netrc`
+    /// (the `password` of the entry for `host`).
+    pub fn from_env(host: &str) -> Result<Option<Self>, String> {
+        if let Some(value) = env::var("GITHUB_TOKEN")
+            .ok()
+            .filter(|value| !value.is_empty())
+        {
+            return Token::new(&value)
+                .map(Some)
+                .map_err(|err| format!("Invalid GITHUB_TOKEN: {}", err));
+        }
+
+        let path = match dirs::home_dir() {
+            Some(home) => home.join(".netrc"),
+            None => return Ok(None),
+        };
+        // not having a `.netrc` is fine
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => return Ok(None),
+        };
+
+        netrc_password(&content, host)
+            .map(Token::new)
+            .transpose()
+            .map_err(|err| format!("Invalid token in {}: {}", path.display(), err))
+    }
+
+    /// The token given with `--token-file` if there is one, otherwise the one `from_env` finds
+    /// for the host of `base_url`.
+    pub fn resolve(token_file: Option<Token>, base_url: &str) -> Result<Option<Self>, String> {
+        if token_file.is_some() {
+            return Ok(token_file);
+        }
+        let host = base_url
+            .parse::<Uri>()
+            .ok()
+            .and_then(|uri| uri.host().map(str::to_string))
+            .unwrap_or_default();
+        Token::from_env(&host)
+    }
+
+    /// The value for the `Authorization` header. It is marked as sensitive, so `http` doesn't
+    /// print it when the request is logged.
+    pub fn header_value(&self) -> HeaderValue {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", self.0)).unwrap();
+        value.set_sensitive(true);
+        value
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Token(***)")
+    }
+}
+
+// supports `machine <host> login <name> password <token>` entries and a trailing `default` entry
+fn netrc_password<'a>(content: &'a str, host: &str) -> Option<&'a str> {
+    let mut words = content.split_whitespace();
+    let mut is_match = false;
+
+    while let Some(word) = words.next() {
+        match word {
+            "machine" => is_match = words.next() == Some(host),
+            "default" => is_match = true,
+            "password" => {
+                let password = words.next();
+                if is_match {
+                    return password;
+                }
+            }
+            "login" | "account" => {
+                words.next();
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_password_of_machine() {
+        let netrc = "machine example.com login a password first\n\
+                     machine api.github.com\n  login octocat\n  password secret\n";
+        assert_eq!(netrc_password(netrc, "api.github.com"), Some("secret"));
+        assert_eq!(netrc_password(netrc, "example.com"), Some("first"));
+        assert_eq!(netrc_password(netrc, "github.com"), None);
+    }
+
+    #[test]
+    fn falls_back_to_default_entry() {
+        let netrc = "machine api.github.com login octocat password secret\n\
+                     default login anonymous password fallback\n";
+        assert_eq!(netrc_password(netrc, "api.github.com"), Some("secret"));
+        assert_eq!(netrc_password(netrc, "ghe.example.com"), Some("fallback"));
+        assert_eq!(netrc_password("default password only", "any"), Some("only"));
+    }
+
+    #[test]
+    fn skips_login_and_account_values() {
+        // a value which happens to be a keyword isn't taken for one
+        let netrc = "machine other login password account default password wrong\n\
+                     machine api.github.com password right";
+        assert_eq!(netrc_password(netrc, "api.github.com"), Some("right"));
+    }
+
+    #[test]
+    fn prefers_token_file() {
+        let token = Token::new("from-file").unwrap();
+        let resolved = Token::resolve(Some(token), "https://api.github.com").unwrap();
+        assert_eq!(resolved.unwrap().0, "from-file");
+    }
+}
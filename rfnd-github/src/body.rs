@@ -0,0 +1,161 @@
+use bytes::Bytes;
+use hyper::body::{Body, Frame, SizeHint};
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+// how much of a streamed body is read at once
+const CHUNK_SIZE: usize = 64 * 1024;
+
+type Reader = Pin<Box<dyn AsyncRead + Send + Sync>>;
+
+/// The body of a request, see `GithubClient::send_body`.
+///
+/// Cloning a body which is streamed from a reader doesn't duplicate the stream: only one of the
+/// clones can be sent, which is why such requests are never retried.
+pub struct RequestBody(Kind);
+
+enum Kind {
+    Empty,
+    // `None` once it was sent
+    Bytes(Option<Bytes>),
+    Reader {
+        // the reader is moved out of here when the body is sent for the first time
+        shared: Arc<Mutex<Option<Reader>>>,
+        state: ReaderState,
+    },
+}
+
+enum ReaderState {
+    NotStarted,
+    Reading(Reader, Vec<u8>),
+    Done,
+}
+
+impl RequestBody {
+    pub fn empty() -> Self {
+        RequestBody(Kind::Empty)
+    }
+
+    /// A body which is completely known upfront, so it is sent with a `Content-Length`.
+    pub fn bytes(bytes: impl Into<Bytes>) -> Self {
+        RequestBody(Kind::Bytes(Some(bytes.into())))
+    }
+
+    /// Streams the body from a reader, e.g. from stdin, with `Transfer-Encoding: chunked`.
+    pub fn reader(reader: impl AsyncRead + Send + Sync + 'static) -> Self {
+        RequestBody(Kind::Reader {
+            shared: Arc::new(Mutex::new(Some(Box::pin(reader)))),
+            state: ReaderState::NotStarted,
+        })
+    }
+
+    /// Whether the body can be sent again, e.g. for a retry.
+    pub fn is_repeatable(&self) -> bool {
+        !matches!(self.0, Kind::Reader { .. })
+    }
+}
+
+impl Clone for RequestBody {
+    fn clone(&self) -> Self {
+        RequestBody(match &self.0 {
+            Kind::Empty => Kind::Empty,
+            Kind::Bytes(bytes) => Kind::Bytes(bytes.clone()),
+            Kind::Reader { shared, .. } => Kind::Reader {
+                shared: Arc::clone(shared),
+                state: ReaderState::NotStarted,
+            },
+        })
+    }
+}
+
+impl Default for RequestBody {
+    fn default() -> Self {
+        RequestBody::empty()
+    }
+}
+
+impl fmt::Debug for RequestBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Kind::Empty => f.write_str("RequestBody::Empty"),
+            Kind::Bytes(bytes) => write!(
+                f,
+                "RequestBody::Bytes({} bytes)",
+                bytes.as_ref().map_or(0, Bytes::len)
+            ),
+            Kind::Reader { .. } => f.write_str("RequestBody::Reader"),
+        }
+    }
+}
+
+impl Body for RequestBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        let (shared, state) = match &mut self.get_mut().0 {
+            Kind::Empty => return Poll::Ready(None),
+            Kind::Bytes(bytes) => {
+                return Poll::Ready(bytes.take().map(|bytes| Ok(Frame::data(bytes))))
+            }
+            Kind::Reader { shared, state } => (shared, state),
+        };
+
+        if let ReaderState::NotStarted = state {
+            let reader = shared
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or_else(|| io::Error::other("the request body was already sent"));
+            match reader {
+                Ok(reader) => *state = ReaderState::Reading(reader, vec![0; CHUNK_SIZE]),
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+
+        let (reader, buf) = match state {
+            ReaderState::Reading(reader, buf) => (reader, buf),
+            _ => return Poll::Ready(None),
+        };
+        let mut read_buf = ReadBuf::new(buf);
+        match reader.as_mut().poll_read(cx, &mut read_buf) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(err)) => Poll::Ready(Some(Err(err))),
+            Poll::Ready(Ok(())) if read_buf.filled().is_empty() => {
+                *state = ReaderState::Done;
+                Poll::Ready(None)
+            }
+            Poll::Ready(Ok(())) => {
+                let data = Bytes::copy_from_slice(read_buf.filled());
+                Poll::Ready(Some(Ok(Frame::data(data))))
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        matches!(
+            &self.0,
+            Kind::Empty
+                | Kind::Bytes(None)
+                | Kind::Reader {
+                    state: ReaderState::Done,
+                    ..
+                }
+        )
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match &self.0 {
+            Kind::Empty | Kind::Bytes(None) => SizeHint::with_exact(0),
+            Kind::Bytes(Some(bytes)) => SizeHint::with_exact(bytes.len() as u64),
+            Kind::Reader { .. } => SizeHint::default(),
+        }
+    }
+}
@@ -0,0 +1,557 @@
+use crate::body::RequestBody;
+use crate::encoding::{BodySize, Decoder};
+use crate::error::Error;
+use crate::fixtures::{ResponseBody, Transport};
+use crate::retry::{self, RetryPolicy};
+use bytes::Bytes;
+use hyper::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CACHE_CONTROL,
+    ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE, VARY,
+};
+use hyper::{Method, Request, Response, StatusCode, Uri};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime};
+
+// how much of a cached body is read at once
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Options of the response cache, which are command line arguments with the `clap` feature.
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+#[derive(Debug, Clone, Default)]
+pub struct CacheOptions {
+    /// Neither read from nor write to the response cache
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub no_cache: bool,
+
+    /// Use cached responses younger than this many seconds without asking the server at all
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "SECONDS", default_value_t = 0)
+    )]
+    pub cache_ttl: u64,
+
+    /// Where responses are cached [default: the user's cache directory]
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "DIR", env = "RFND_CACHE_DIR")
+    )]
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl CacheOptions {
+    /// Returns `None` if caching is disabled or there is no cache directory on this system.
+    pub fn cache(&self) -> Option<Cache> {
+        if self.no_cache {
+            return None;
+        }
+
+        let dir = self
+            .cache_dir
+            .clone()
+            .or_else(|| dirs::cache_dir().map(|dir| dir.join("rust-for-node-developers")))?;
+
+        Some(Cache::new(dir, Duration::from_secs(self.cache_ttl)))
+    }
+}
+
+#[cfg_attr(feature = "clap", derive(clap::Subcommand))]
+#[derive(Debug, Clone, Copy)]
+pub enum CacheCommand {
+    /// Show the cache directory and all cached responses
+    List,
+    /// Remove all cached responses
+    Clear,
+}
+
+/// A response whose body is read chunk by chunk, either from the network or from the cache.
+///
+/// The body is decompressed according to its `Content-Encoding`, but the headers are left as
+/// the server sent them.
+#[derive(Debug)]
+pub struct FetchedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// Whether the body comes from the cache (after the server confirmed it with a `304`, or
+    /// without asking the server at all when the entry is younger than the TTL).
+    pub from_cache: bool,
+    body: Body,
+    decoder: Decoder,
+    size: BodySize,
+}
+
+#[derive(Debug)]
+enum Body {
+    Network {
+        body: ResponseBody,
+        writer: Option<Box<EntryWriter>>,
+    },
+    Cached(File),
+}
+
+impl FetchedResponse {
+    fn new(status: StatusCode, headers: HeaderMap, body: Body) -> Result<Self, Error> {
+        Ok(FetchedResponse {
+            status,
+            decoder: Decoder::from_headers(&headers)?,
+            headers,
+            from_cache: matches!(body, Body::Cached(_)),
+            body,
+            size: BodySize::default(),
+        })
+    }
+
+    fn from_network(
+        res: Response<ResponseBody>,
+        writer: Option<Box<EntryWriter>>,
+    ) -> Result<Self, Error> {
+        let (parts, body) = res.into_parts();
+        FetchedResponse::new(parts.status, parts.headers, Body::Network { body, writer })
+    }
+
+    /// Returns the next decompressed chunk of the body or `None` at its end.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, Error> {
+        loop {
+            let data = match self.raw_chunk().await? {
+                Some(data) => data,
+                None => {
+                    let rest = self.decoder.finish()?;
+                    self.size.decoded += rest.len() as u64;
+                    return Ok(Some(rest).filter(|rest| !rest.is_empty()));
+                }
+            };
+
+            self.size.encoded += data.len() as u64;
+            let data = self.decoder.push(data)?;
+            if !data.is_empty() {
+                self.size.decoded += data.len() as u64;
+                return Ok(Some(data));
+            }
+        }
+    }
+
+    /// How much of the body was read so far.
+    pub fn body_size(&self) -> BodySize {
+        self.size
+    }
+
+    // the body as it was sent, which is what the cache stores - a body from the network is
+    // written to the cache while it is read
+    async fn raw_chunk(&mut self) -> Result<Option<Bytes>, Error> {
+        match &mut self.body {
+            Body::Cached(file) => read_chunk(file).map_err(Error::Cache),
+            Body::Network { body, writer } => {
+                let data = match body.chunk().await? {
+                    Some(data) => data,
+                    None => {
+                        if let Some(writer) = writer.take() {
+                            writer.finish();
+                        }
+                        return Ok(None);
+                    }
+                };
+
+                if let Some(entry_writer) = writer {
+                    if let Err(err) = entry_writer.file.write_all(&data) {
+                        entry_writer.warn(err);
+                        *writer = None;
+                    }
+                }
+                Ok(Some(data))
+            }
+        }
+    }
+
+    /// Reads the rest of the body into memory.
+    pub async fn bytes(&mut self) -> Result<Bytes, Error> {
+        let mut buf = Vec::new();
+        while let Some(chunk) = self.chunk().await? {
+            buf.extend_from_slice(&chunk);
+        }
+        Ok(buf.into())
+    }
+}
+
+/// Stores successful `GET` responses together with their `ETag` and `Last-Modified` headers, so
+/// they can be revalidated with a conditional request. GitHub doesn't count `304 Not Modified`
+/// responses against the rate limit.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+// the meta data of a cached response, the body is stored next to it
+#[derive(Serialize, Deserialize, Debug)]
+struct Entry {
+    url: String,
+    stored_at: SystemTime,
+    headers: Vec<(String, String)>,
+    /// The request headers named in the response's `Vary` header and what they were set to.
+    #[serde(default)]
+    vary: Vec<(String, Option<String>)>,
+}
+
+impl Cache {
+    /// Responses younger than `ttl` are used without asking the server at all.
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Cache { dir, ttl }
+    }
+
+    /// Runs one of the `cache` subcommands.
+    pub fn run(&self, command: CacheCommand) -> io::Result<()> {
+        match command {
+            CacheCommand::List => self.list(),
+            CacheCommand::Clear => self.clear(),
+        }
+    }
+
+    fn list(&self) -> io::Result<()> {
+        println!("Cache directory: {}", self.dir.display());
+
+        for (entry, body_len) in self.entries()? {
+            let age = entry.stored_at.elapsed().unwrap_or_default();
+            println!(
+                "{:>8}s old {:>10} bytes  {}",
+                age.as_secs(),
+                body_len,
+                entry.url
+            );
+        }
+        Ok(())
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        let count = self.entries()?.len();
+        match fs::remove_dir_all(&self.dir) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        println!("Removed {} cached responses.", count);
+        Ok(())
+    }
+
+    fn entries(&self) -> io::Result<Vec<(Entry, u64)>> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            read_dir => read_dir?,
+        };
+
+        let mut entries = Vec::new();
+        for file in read_dir {
+            let path = file?.path();
+            if path.extension() != Some("json".as_ref()) {
+                continue;
+            }
+            let key = path.file_stem().unwrap_or_default().to_string_lossy();
+            if let Some((entry, body)) = self.load(&key) {
+                entries.push((entry, body.metadata()?.len()));
+            }
+        }
+
+        entries.sort_by(|(a, _), (b, _)| a.url.cmp(&b.url));
+        Ok(entries)
+    }
+
+    // the same URL can return different data for different users and in different
+    // representations - the server can name even more headers in `Vary`, see `Entry::matches`
+    fn key<B>(req: &Request<B>) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(req.uri().to_string());
+        for name in [AUTHORIZATION, ACCEPT, ACCEPT_ENCODING] {
+            if let Some(value) = req.headers().get(&name) {
+                hasher.update(b"\n");
+                hasher.update(name.as_str());
+                hasher.update(b": ");
+                hasher.update(value.as_bytes());
+            }
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    // a broken or half written entry is just a cache miss
+    fn load(&self, key: &str) -> Option<(Entry, File)> {
+        let meta = fs::read(self.dir.join(format!("{}.json", key))).ok()?;
+        let entry = serde_json::from_slice(&meta).ok()?;
+        let body = File::open(self.dir.join(format!("{}.body", key))).ok()?;
+        Some((entry, body))
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        for extension in ["json", "body"] {
+            match fs::remove_file(self.dir.join(format!("{}.{}", key, extension))) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn store_entry(&self, key: &str, entry: &Entry) -> io::Result<()> {
+        let meta = serde_json::to_vec(entry)?;
+        create_private_file(&self.dir.join(format!("{}.json", key)))?.write_all(&meta)
+    }
+
+    fn writer(&self, key: &str, entry: Entry) -> io::Result<Box<EntryWriter>> {
+        create_private_dir(&self.dir)?;
+        // several processes could fetch the same URL at the same time
+        let tmp_path = self.dir.join(format!("{}.body.{}.tmp", key, process::id()));
+
+        Ok(Box::new(EntryWriter {
+            file: create_private_file(&tmp_path)?,
+            cache: self.clone(),
+            key: key.to_string(),
+            entry,
+            tmp_path,
+        }))
+    }
+}
+
+// responses sent with a token can contain private data, so other users must not read the cache
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::fs::DirBuilder;
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    // older versions created it readable for everyone
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)
+}
+
+fn create_private_file(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+// streams a body into the cache, the entry only shows up once the body is complete
+#[derive(Debug)]
+struct EntryWriter {
+    cache: Cache,
+    key: String,
+    entry: Entry,
+    file: File,
+    tmp_path: PathBuf,
+}
+
+impl EntryWriter {
+    fn finish(self) {
+        let body_path = self.cache.dir.join(format!("{}.body", self.key));
+        // write the meta data last, so readers never see it without its body
+        let result = fs::rename(&self.tmp_path, body_path)
+            .and_then(|_| self.cache.store_entry(&self.key, &self.entry));
+        if let Err(err) = result {
+            self.warn(err);
+        }
+    }
+
+    fn warn(&self, err: io::Error) {
+        eprintln!(
+            "Couldn't write to cache {}: {}",
+            self.cache.dir.display(),
+            err
+        );
+    }
+}
+
+impl Drop for EntryWriter {
+    // the body wasn't read completely or couldn't be written
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.tmp_path);
+    }
+}
+
+impl Entry {
+    /// Returns `None` if the response must not be cached, because it varies on something other
+    /// than request headers (`Vary: *`).
+    fn new(url: &Uri, request_headers: &HeaderMap, headers: &HeaderMap) -> Option<Self> {
+        let mut vary = Vec::new();
+        let names = headers
+            .get_all(VARY)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|name| name.trim().to_ascii_lowercase());
+        for name in names {
+            if name == "*" {
+                return None;
+            }
+            // GitHub names some headers twice
+            if name.is_empty() || vary.iter().any(|(known, _)| *known == name) {
+                continue;
+            }
+            let value = request_headers
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            vary.push((name, value));
+        }
+
+        Some(Entry {
+            url: url.to_string(),
+            stored_at: SystemTime::now(),
+            headers: header_pairs(headers),
+            vary,
+        })
+    }
+
+    /// Whether the response was stored for a request with the same headers as this one, as far
+    /// as the response varies on them.
+    fn matches(&self, request_headers: &HeaderMap) -> bool {
+        self.vary.iter().all(|(name, value)| {
+            let current = request_headers
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok());
+            current == value.as_deref()
+        })
+    }
+
+    fn header_map(&self) -> HeaderMap {
+        header_map(&self.headers)
+    }
+}
+
+// headers are stored as pairs of strings, which is easier to read than what `http` would give us
+pub(crate) fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+pub(crate) fn header_map(pairs: &[(String, String)]) -> HeaderMap {
+    pairs
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+            let value = HeaderValue::from_str(value).ok()?;
+            Some((name, value))
+        })
+        .collect()
+}
+
+/// Reads the next chunk of a stored body, `None` at its end.
+pub(crate) fn read_chunk(file: &mut File) -> io::Result<Option<Bytes>> {
+    let mut buf = vec![0; CHUNK_SIZE];
+    let len = file.read(&mut buf)?;
+    buf.truncate(len);
+    Ok(if len == 0 { None } else { Some(buf.into()) })
+}
+
+/// Like `retry::send`, but answers `GET` requests from the cache if possible. Successful
+/// responses are written to the cache while their body is read, unless the request has a
+/// `Cache-Control: no-store` header.
+pub(crate) async fn send<F>(
+    transport: &Transport,
+    policy: RetryPolicy,
+    cache: Option<&Cache>,
+    build: F,
+) -> Result<FetchedResponse, Error>
+where
+    F: Fn() -> Request<RequestBody>,
+{
+    let probe = build();
+    let (cache, key) = match cache {
+        Some(cache) if is_cacheable(&probe) => (cache, Cache::key(&probe)),
+        _ => return fetch(transport, policy, build).await,
+    };
+
+    let cached = cache
+        .load(&key)
+        .filter(|(entry, _)| entry.matches(probe.headers()));
+    let cached = match cached {
+        Some((entry, body)) if entry.stored_at.elapsed().unwrap_or_default() < cache.ttl => {
+            return FetchedResponse::new(StatusCode::OK, entry.header_map(), Body::Cached(body));
+        }
+        cached => cached,
+    };
+
+    let res = retry::send(transport, policy, || {
+        let mut req = build();
+        if let Some((entry, _)) = &cached {
+            let headers = entry.header_map();
+            if let Some(etag) = headers.get(ETAG) {
+                req.headers_mut().insert(IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(last_modified) = headers.get(LAST_MODIFIED) {
+                req.headers_mut()
+                    .insert(IF_MODIFIED_SINCE, last_modified.clone());
+            }
+        }
+        req
+    })
+    .await?;
+
+    match (res.status(), cached) {
+        (StatusCode::NOT_MODIFIED, Some((entry, body))) => {
+            // the `304` has fresh rate limit headers, but e.g. no `Link` header
+            let mut headers = entry.header_map();
+            headers.extend(res.headers().clone());
+
+            let result = match Entry::new(probe.uri(), probe.headers(), &headers) {
+                Some(entry) => cache.store_entry(&key, &entry),
+                None => cache.remove(&key),
+            };
+            if let Err(err) = result {
+                eprintln!("Couldn't write to cache {}: {}", cache.dir.display(), err);
+            }
+
+            FetchedResponse::new(StatusCode::OK, headers, Body::Cached(body))
+        }
+        (StatusCode::OK, _) => {
+            let writer = match Entry::new(probe.uri(), probe.headers(), res.headers()) {
+                Some(entry) => cache.writer(&key, entry),
+                None => return FetchedResponse::from_network(res, None),
+            };
+            let writer = match writer {
+                Ok(writer) => Some(writer),
+                Err(err) => {
+                    eprintln!("Couldn't write to cache {}: {}", cache.dir.display(), err);
+                    None
+                }
+            };
+            FetchedResponse::from_network(res, writer)
+        }
+        _ => FetchedResponse::from_network(res, None),
+    }
+}
+
+fn is_cacheable<B>(req: &Request<B>) -> bool {
+    let no_store = req
+        .headers()
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"));
+    // a part of a response must not replace the whole one in the cache
+    req.method() == Method::GET && !req.headers().contains_key(RANGE) && !no_store
+}
+
+async fn fetch<F>(
+    transport: &Transport,
+    policy: RetryPolicy,
+    build: F,
+) -> Result<FetchedResponse, Error>
+where
+    F: Fn() -> Request<RequestBody>,
+{
+    let res = retry::send(transport, policy, build).await?;
+    FetchedResponse::from_network(res, None)
+}
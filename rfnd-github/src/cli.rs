@@ -0,0 +1,33 @@
+//! What the command line programs of the examples share. Unlike the rest of the crate, these
+//! functions end the process.
+
+use crate::cache::{CacheCommand, CacheOptions};
+use std::io::{self, ErrorKind};
+use std::process::exit;
+
+/// Runs one of the `cache` subcommands and exits, with `2` if there is no cache directory and
+/// `1` if it couldn't be accessed.
+pub fn run_cache_command(options: &CacheOptions, command: CacheCommand) -> ! {
+    let cache = options.cache().unwrap_or_else(|| {
+        eprintln!("There is no cache directory, see --help");
+        exit(2);
+    });
+    if let Err(err) = cache.run(command) {
+        eprintln!("Couldn't access cache: {}", err);
+        exit(1);
+    }
+    exit(0);
+}
+
+/// Unwraps the result of writing to stdout. Exits if the output can't be written, but not if it
+/// is just closed early (e.g. by `head`).
+pub fn check_output<T>(result: io::Result<T>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => exit(0),
+        Err(err) => {
+            eprintln!("Couldn't write output: {}", err);
+            exit(1);
+        }
+    }
+}
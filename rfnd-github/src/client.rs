@@ -0,0 +1,604 @@
+use crate::auth::Token;
+use crate::body::RequestBody;
+use crate::cache::{self, Cache, FetchedResponse};
+use crate::encoding::{BodySize, ACCEPT_ENCODING};
+use crate::error::Error;
+use crate::fixtures::{Fixtures, Transport};
+use crate::graphql::{GraphqlResponse, PageInfo};
+use crate::json_stream::ArrayParser;
+use crate::proxy::ProxyConnector;
+use crate::rate_limit::RateLimit;
+use crate::repository::Repository;
+use crate::retry::RetryPolicy;
+use crate::timeout::{TimeoutConnector, TimeoutOptions};
+use crate::tls::TlsOptions;
+use crate::trace::{Event, TraceConnector, TraceResolver, Tracer};
+use crate::user::User;
+use hyper::header::{
+    self, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, LINK, LOCATION, USER_AGENT,
+};
+use hyper::{HeaderMap, Method, Request, StatusCode, Uri};
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use std::ops::ControlFlow;
+use std::str::from_utf8;
+
+// how many redirects in a row are followed, e.g. before a loop is given up
+const MAX_REDIRECTS: usize = 10;
+
+pub(crate) type HttpsClient = Client<TimeoutConnector<TraceConnector>, RequestBody>;
+
+/// A client for the GitHub REST and GraphQL APIs.
+///
+/// Requests are retried on temporary failures, redirects are followed, `GET` responses are cached
+/// if a cache is configured, and all requests share one connection pool. Cloning the client is
+/// cheap.
+#[derive(Debug, Clone)]
+pub struct GithubClient {
+    transport: Transport,
+    base_url: String,
+    headers: HeaderMap,
+    retry: RetryPolicy,
+    cache: Option<Cache>,
+}
+
+/// Configures a `GithubClient`, see `GithubClient::builder`.
+#[derive(Debug, Clone)]
+pub struct GithubClientBuilder {
+    base_url: String,
+    headers: HeaderMap,
+    retry: RetryPolicy,
+    cache: Option<Cache>,
+    fixtures: Option<Fixtures>,
+    tls: TlsOptions,
+    timeouts: TimeoutOptions,
+    tracer: Tracer,
+}
+
+/// One page of a paginated listing.
+#[derive(Debug)]
+pub struct Page {
+    /// The URL of the next page, taken from the `Link` header.
+    pub next_url: Option<String>,
+    pub rate_limit: Option<RateLimit>,
+    pub from_cache: bool,
+    /// How much of the body was read, which is all of it unless `is_stopped` is set.
+    pub body_size: BodySize,
+    /// Whether the caller didn't want any more items.
+    pub is_stopped: bool,
+}
+
+impl GithubClient {
+    pub fn builder() -> GithubClientBuilder {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            USER_AGENT,
+            HeaderValue::from_static("Mercateo/rust-for-node-developers"),
+        );
+        // bodies are decompressed while they are read, see `FetchedResponse::chunk`
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static(ACCEPT_ENCODING),
+        );
+
+        GithubClientBuilder {
+            base_url: "https://api.github.com".to_string(),
+            headers,
+            retry: RetryPolicy::new(3),
+            cache: None,
+            fixtures: None,
+            tls: TlsOptions::default(),
+            timeouts: TimeoutOptions::default(),
+            tracer: Tracer::default(),
+        }
+    }
+
+    /// An anonymous client for api.github.com with the default settings.
+    pub fn new() -> Result<Self, Error> {
+        GithubClient::builder().build()
+    }
+
+    /// Resolves a path like `/users/octocat` against the base URL. Absolute URLs (e.g. from a
+    /// `Link` header) are returned unchanged.
+    pub fn url(&self, path: &str) -> String {
+        if path.starts_with("http://") || path.starts_with("https://") {
+            path.to_string()
+        } else {
+            format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+        }
+    }
+
+    /// Sends a request and returns the response whatever its status is. `GET` requests are
+    /// answered from the cache if possible.
+    pub async fn send(&self, method: Method, path: &str) -> Result<FetchedResponse, Error> {
+        self.send_body(method, path, RequestBody::empty()).await
+    }
+
+    /// Like `send`, but with a body. A body which is streamed from a reader can only be sent
+    /// once, so the request isn't retried then.
+    pub async fn send_body(
+        &self,
+        method: Method,
+        path: &str,
+        body: RequestBody,
+    ) -> Result<FetchedResponse, Error> {
+        self.send_with_headers(method, path, HeaderMap::new(), body)
+            .await
+    }
+
+    /// Like `send_body`, but with headers for this request only, which replace the ones of the
+    /// client with the same name. Requests with a `Range` header or `Cache-Control: no-store`
+    /// are neither answered from nor written to the cache.
+    ///
+    /// Redirects are followed like GitHub asks for: the request is sent again as it is, except
+    /// after a `303 See Other`, which is followed with a `GET`. The token is only sent to the
+    /// host the request was meant for.
+    pub async fn send_with_headers(
+        &self,
+        method: Method,
+        path: &str,
+        headers: HeaderMap,
+        body: RequestBody,
+    ) -> Result<FetchedResponse, Error> {
+        let mut all_headers = self.headers.clone();
+        for (name, value) in &headers {
+            all_headers.insert(name, value.clone());
+        }
+
+        let mut uri: Uri = self
+            .url(path)
+            .parse()
+            .map_err(|err| Error::Http(Box::new(err)))?;
+        let (mut method, mut body) = (method, body);
+
+        for _ in 0..=MAX_REDIRECTS {
+            let res = self.send_once(&method, &uri, &all_headers, &body).await?;
+            if !is_redirect(res.status) {
+                return Ok(res);
+            }
+
+            let location = res
+                .headers
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| resolve(&uri, location))
+                .ok_or_else(|| {
+                    Error::Redirect(format!("{} without a valid Location", res.status))
+                })?;
+            if res.status == StatusCode::SEE_OTHER && method != Method::HEAD {
+                method = Method::GET;
+                body = RequestBody::empty();
+                all_headers.remove(CONTENT_TYPE);
+            } else if !body.is_repeatable() {
+                return Err(Error::Redirect(format!(
+                    "the body can't be sent to {} again",
+                    location
+                )));
+            }
+            // e.g. archives are served by another host, which has no use for the token
+            if location.scheme() != uri.scheme() || location.authority() != uri.authority() {
+                all_headers.remove(AUTHORIZATION);
+            }
+            uri = location;
+        }
+
+        Err(Error::Redirect(format!(
+            "more than {} redirects, the last one to {}",
+            MAX_REDIRECTS, uri
+        )))
+    }
+
+    async fn send_once(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+        body: &RequestBody,
+    ) -> Result<FetchedResponse, Error> {
+        let retry = match body.is_repeatable() {
+            true => self.retry,
+            false => RetryPolicy {
+                max_attempts: 1,
+                wait_for_rate_limit: false,
+                ..self.retry
+            },
+        };
+
+        cache::send(&self.transport, retry, self.cache.as_ref(), || {
+            let mut req = Request::new(body.clone());
+            *req.method_mut() = method.clone();
+            *req.uri_mut() = uri.clone();
+            *req.headers_mut() = headers.clone();
+            req
+        })
+        .await
+    }
+
+    /// `GET /users/:user`
+    pub async fn user(&self, login: &str) -> Result<User, Error> {
+        self.get_json(&format!("/users/{}", login)).await
+    }
+
+    /// `GET /repos/:owner/:repo`
+    pub async fn repo(&self, owner: &str, name: &str) -> Result<Repository, Error> {
+        self.get_json(&format!("/repos/{}/{}", owner, name)).await
+    }
+
+    /// All pages of `GET /users/:user/repos`.
+    pub async fn repos(&self, login: &str) -> Result<Vec<Repository>, Error> {
+        let mut repositories = Vec::new();
+        let mut url = Some(format!("/users/{}/repos?per_page=100", login));
+
+        while let Some(next_url) = url {
+            let page = self
+                .page(&next_url, |repository| {
+                    repositories.push(repository);
+                    ControlFlow::Continue(())
+                })
+                .await?;
+            url = page.next_url;
+        }
+
+        Ok(repositories)
+    }
+
+    /// Fetches one page of a listing and passes every item to `on_item` as soon as it is parsed,
+    /// until it returns `Break`. The body is never buffered as a whole.
+    pub async fn page<T, F>(&self, path: &str, mut on_item: F) -> Result<Page, Error>
+    where
+        T: DeserializeOwned,
+        F: FnMut(T) -> ControlFlow<()>,
+    {
+        let mut res = self.send(Method::GET, path).await?;
+        let mut page = Page {
+            next_url: next_link(&res.headers),
+            rate_limit: RateLimit::from_headers(&res.headers),
+            from_cache: res.from_cache,
+            body_size: BodySize::default(),
+            is_stopped: false,
+        };
+
+        if res.status.is_client_error() || res.status.is_server_error() {
+            let status = res.status;
+            let buf = res.bytes().await?;
+            Error::check_status(status, page.rate_limit, from_utf8(&buf)?)?;
+            return Ok(page);
+        }
+
+        // parse the body while it arrives instead of buffering it
+        let mut parser = ArrayParser::new();
+        while let Some(chunk) = res.chunk().await? {
+            for element in parser.push(&chunk)? {
+                if on_item(element.deserialize()?).is_break() {
+                    page.is_stopped = true;
+                    page.body_size = res.body_size();
+                    return Ok(page);
+                }
+            }
+        }
+        parser.finish()?;
+
+        page.body_size = res.body_size();
+        Ok(page)
+    }
+
+    /// `POST /graphql` with a query document and its variables. GitHub only answers queries
+    /// with a token. Errors reported by GraphQL are part of the response, see
+    /// `GraphqlResponse::into_data`.
+    pub async fn graphql<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: &Map<String, Value>,
+    ) -> Result<GraphqlResponse<T>, Error> {
+        let body = json!({ "query": query, "variables": variables });
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        // GitHub Enterprise serves the REST API below `/api/v3`, but GraphQL at `/api/graphql`
+        let url = match self.base_url.strip_suffix("/api/v3") {
+            Some(host) => format!("{}/api/graphql", host),
+            None => self.url("/graphql"),
+        };
+        let body = RequestBody::bytes(body.to_string());
+        let mut res = self
+            .send_with_headers(Method::POST, &url, headers, body)
+            .await?;
+
+        let status = res.status;
+        let rate_limit = RateLimit::from_headers(&res.headers);
+        let buf = res.bytes().await?;
+        let body = from_utf8(&buf)?;
+
+        Error::check_status(status, rate_limit, body)?;
+        Ok(serde_json::from_str(body)?)
+    }
+
+    /// Sends a query which selects a connection again and again with the variable `$cursor` set
+    /// to the end of the previous page, until `page_info` (which picks the connection's
+    /// `pageInfo` out of the data) says there is no next page or `on_page` returns `Break`. Fails
+    /// if GraphQL reports any error.
+    pub async fn graphql_pages<T, P, F>(
+        &self,
+        query: &str,
+        mut variables: Map<String, Value>,
+        page_info: P,
+        mut on_page: F,
+    ) -> Result<(), Error>
+    where
+        T: DeserializeOwned,
+        P: Fn(&T) -> Option<PageInfo>,
+        F: FnMut(T) -> ControlFlow<()>,
+    {
+        let mut previous_cursor = None;
+        loop {
+            let data = self.graphql(query, &variables).await?.into_data()?;
+            let page_info = page_info(&data).unwrap_or_default();
+            // a query which doesn't use `$cursor` gets the same page again, which mustn't be
+            // passed on twice
+            if previous_cursor.is_some() && page_info.end_cursor == previous_cursor {
+                return Ok(());
+            }
+            if on_page(data).is_break() {
+                return Ok(());
+            }
+
+            let cursor = match page_info.next_cursor() {
+                Some(cursor) => cursor.to_string(),
+                None => return Ok(()),
+            };
+            variables.insert("cursor".to_string(), Value::String(cursor.clone()));
+            previous_cursor = Some(cursor);
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let mut res = self.send(Method::GET, path).await?;
+        let status = res.status;
+        let rate_limit = RateLimit::from_headers(&res.headers);
+        let buf = res.bytes().await?;
+        let body = from_utf8(&buf)?;
+
+        Error::check_status(status, rate_limit, body)?;
+        Ok(serde_json::from_str(body)?)
+    }
+}
+
+impl GithubClientBuilder {
+    /// The API to talk to, e.g. `https://github.example.com/api/v3` for GitHub Enterprise or a
+    /// local mock. Defaults to `https://api.github.com`.
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Authenticates all requests with this token. Without one, requests are anonymous.
+    pub fn token(mut self, token: Option<Token>) -> Self {
+        match token {
+            Some(token) => self.headers.insert(AUTHORIZATION, token.header_value()),
+            None => self.headers.remove(AUTHORIZATION),
+        };
+        self
+    }
+
+    /// Adds a header to all requests. A header which is set by default (`User-Agent`) is
+    /// replaced, others can be added several times.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        if name == USER_AGENT {
+            self.headers.insert(name, value);
+        } else {
+            self.headers.append(name, value);
+        }
+        self
+    }
+
+    /// Defaults to `RetryPolicy::new(3)`.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Caches `GET` responses, which is off by default.
+    pub fn cache(mut self, cache: Option<Cache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Records all responses or replays recorded ones instead of using the network. The cache
+    /// is turned off then, so what is recorded or replayed doesn't depend on it.
+    pub fn fixtures(mut self, fixtures: Option<Fixtures>) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    pub fn tls(mut self, options: TlsOptions) -> Self {
+        self.tls = options;
+        self
+    }
+
+    pub fn timeouts(mut self, options: TimeoutOptions) -> Self {
+        self.timeouts = options;
+        self
+    }
+
+    /// Calls `on_event` for everything that happens while requests are sent, e.g. to print
+    /// them like `curl -v`. The callback must be quick, it runs in the middle of the request.
+    pub fn trace<F>(mut self, on_event: F) -> Self
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.tracer = Tracer::new(on_event);
+        self
+    }
+
+    pub fn build(self) -> Result<GithubClient, Error> {
+        let mut http = HttpConnector::new_with_resolver(TraceResolver::new(self.tracer.clone()));
+        // allow `https://` URLs, the `HttpsConnector` takes care of them
+        http.enforce_http(false);
+
+        let tls = self.tls.connector()?;
+        let proxy = ProxyConnector::from_env(http, self.tracer.clone());
+        let https = HttpsConnector::from((proxy, tls.into()));
+
+        let connector = TimeoutConnector::new(
+            TraceConnector::new(https, self.tracer.clone()),
+            &self.timeouts,
+        );
+
+        let http = Client::builder(TokioExecutor::new()).build(connector);
+        // recorded exchanges must not depend on what happens to be cached
+        let cache = match self.fixtures {
+            Some(_) => None,
+            None => self.cache,
+        };
+
+        Ok(GithubClient {
+            transport: Transport::new(http, self.fixtures, self.tracer),
+            base_url: self.base_url,
+            headers: self.headers,
+            retry: self.retry,
+            cache,
+        })
+    }
+}
+
+/// Checks that a base URL (see `GithubClientBuilder::base_url`) is an absolute http(s) URL and
+/// removes a trailing `/`, usable as a clap value parser.
+pub fn parse_base_url(value: &str) -> Result<String, String> {
+    let uri: Uri = value.parse().map_err(|err| format!("{}", err))?;
+    match uri.scheme_str() {
+        Some("http") | Some("https") if uri.authority().is_some() => {
+            Ok(value.trim_end_matches('/').to_string())
+        }
+        _ => Err("expected an absolute http(s) URL".to_string()),
+    }
+}
+
+// parses a header like `<https://api.github.com/...?page=2>; rel="next", <...>; rel="last"`
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let mut parts = link.split(';');
+            let url = parts.next()?.trim();
+            let is_next = parts.any(|param| param.trim() == "rel=\"next\"");
+
+            if is_next && url.starts_with('<') && url.ends_with('>') {
+                Some(url[1..url.len() - 1].to_string())
+            } else {
+                None
+            }
+        })
+}
+
+// `304 Not Modified` is no redirect, and `300 Multiple Choices` leaves the choice to the user
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+// `Location` is either an absolute URL or relative to the URL which was redirected
+fn resolve(base: &Uri, location: &str) -> Option<Uri> {
+    let origin = format!("{}://{}", base.scheme_str()?, base.authority()?);
+    let url = if location.starts_with("http://") || location.starts_with("https://") {
+        location.to_string()
+    } else if let Some(rest) = location.strip_prefix("//") {
+        format!("{}://{}", base.scheme_str()?, rest)
+    } else if location.starts_with('/') {
+        format!("{}{}", origin, location)
+    } else {
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map_or(0, |slash| slash + 1)];
+        format!("{}{}{}", origin, dir, location)
+    };
+    url.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(LINK, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn finds_next_link() {
+        let headers = links(&[
+            "<https://api.github.com/user/1/repos?page=1>; rel=\"prev\", \
+             <https://api.github.com/user/1/repos?page=3>; rel=\"next\", \
+             <https://api.github.com/user/1/repos?page=5>; rel=\"last\"",
+        ]);
+        assert_eq!(
+            next_link(&headers).as_deref(),
+            Some("https://api.github.com/user/1/repos?page=3")
+        );
+    }
+
+    #[test]
+    fn finds_next_link_in_any_header() {
+        let headers = links(&[
+            "<https://api.github.com/a?page=1>; rel=\"first\"",
+            "<https://api.github.com/a?page=2>;rel=\"next\"",
+        ]);
+        assert_eq!(
+            next_link(&headers).as_deref(),
+            Some("https://api.github.com/a?page=2")
+        );
+    }
+
+    #[test]
+    fn ignores_missing_next_link() {
+        assert_eq!(next_link(&HeaderMap::new()), None);
+        assert_eq!(
+            next_link(&links(&["<https://api.github.com/a?page=1>; rel=\"prev\""])),
+            None
+        );
+        assert_eq!(
+            next_link(&links(&["https://api.github.com/a; rel=\"next\""])),
+            None
+        );
+    }
+
+    #[test]
+    fn resolves_redirect_locations() {
+        let base: Uri = "https://api.github.com/repos/octocat/old/tarball?ref=main"
+            .parse()
+            .unwrap();
+        let resolved = |location| resolve(&base, location).unwrap().to_string();
+
+        assert_eq!(
+            resolved("https://codeload.github.com/octocat/new/legacy.tar.gz/main"),
+            "https://codeload.github.com/octocat/new/legacy.tar.gz/main"
+        );
+        assert_eq!(
+            resolved("//codeload.github.com/archive"),
+            "https://codeload.github.com/archive"
+        );
+        assert_eq!(
+            resolved("/repositories/1296269/tarball"),
+            "https://api.github.com/repositories/1296269/tarball"
+        );
+        assert_eq!(
+            resolved("zipball?ref=main"),
+            "https://api.github.com/repos/octocat/old/zipball?ref=main"
+        );
+        assert!(resolve(&base, "https://exa mple.com/").is_none());
+    }
+}
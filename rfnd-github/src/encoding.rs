@@ -0,0 +1,119 @@
+use crate::error::Error;
+use brotli_decompressor::DecompressorWriter;
+use bytes::Bytes;
+use flate2::write::{GzDecoder, ZlibDecoder};
+use hyper::header::{HeaderMap, HeaderValue, CONTENT_ENCODING};
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+
+/// The encodings we can decode, in the order we prefer them.
+pub(crate) const ACCEPT_ENCODING: &str = "br, gzip, deflate";
+
+// how much decoded data the brotli decoder buffers before passing it on
+const BROTLI_BUFFER_SIZE: usize = 16 * 1024;
+
+/// How big a body is as it was sent and after it was decompressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BodySize {
+    /// The bytes received from the server (or read from the cache).
+    pub encoded: u64,
+    pub decoded: u64,
+}
+
+impl fmt::Display for BodySize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.encoded == self.decoded {
+            write!(f, "{} bytes", self.decoded)
+        } else {
+            write!(
+                f,
+                "{} bytes compressed, {} bytes decompressed",
+                self.encoded, self.decoded
+            )
+        }
+    }
+}
+
+/// Decompresses a body chunk by chunk according to its `Content-Encoding`. Every decoder
+/// writes into a `Vec`, which is emptied after every chunk.
+pub(crate) enum Decoder {
+    Identity,
+    Gzip(GzDecoder<Vec<u8>>),
+    Deflate(ZlibDecoder<Vec<u8>>),
+    Brotli(Box<DecompressorWriter<Vec<u8>>>),
+}
+
+impl Decoder {
+    pub fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let encoding = match headers.get(CONTENT_ENCODING).map(HeaderValue::to_str) {
+            None => return Ok(Decoder::Identity),
+            Some(Ok(encoding)) => encoding.trim().to_ascii_lowercase(),
+            Some(Err(err)) => return Err(Error::Http(Box::new(err))),
+        };
+
+        match encoding.as_str() {
+            "identity" | "" => Ok(Decoder::Identity),
+            "gzip" | "x-gzip" => Ok(Decoder::Gzip(GzDecoder::new(Vec::new()))),
+            "deflate" => Ok(Decoder::Deflate(ZlibDecoder::new(Vec::new()))),
+            "br" => Ok(Decoder::Brotli(Box::new(DecompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER_SIZE,
+            )))),
+            // we never ask for anything else, e.g. several encodings at once
+            _ => Err(Error::Http(
+                format!("Unsupported Content-Encoding: {}", encoding).into(),
+            )),
+        }
+    }
+
+    /// Decodes the next chunk. The result can be empty if the decoder needs more input.
+    pub fn push(&mut self, chunk: Bytes) -> Result<Bytes, Error> {
+        let result = match self {
+            Decoder::Identity => return Ok(chunk),
+            Decoder::Gzip(decoder) => decoder.write_all(&chunk),
+            Decoder::Deflate(decoder) => decoder.write_all(&chunk),
+            Decoder::Brotli(decoder) => decoder.write_all(&chunk),
+        };
+        result.map_err(corrupt)?;
+        Ok(self.take())
+    }
+
+    /// Returns what is left at the end of the body and fails if the body ended too early.
+    pub fn finish(&mut self) -> Result<Bytes, Error> {
+        let result = match self {
+            Decoder::Identity => Ok(()),
+            Decoder::Gzip(decoder) => decoder.try_finish(),
+            Decoder::Deflate(decoder) => decoder.try_finish(),
+            Decoder::Brotli(decoder) => decoder.close(),
+        };
+        result.map_err(corrupt)?;
+        Ok(self.take())
+    }
+
+    fn take(&mut self) -> Bytes {
+        let buf = match self {
+            Decoder::Identity => return Bytes::new(),
+            Decoder::Gzip(decoder) => decoder.get_mut(),
+            Decoder::Deflate(decoder) => decoder.get_mut(),
+            Decoder::Brotli(decoder) => decoder.get_mut(),
+        };
+        mem::take(buf).into()
+    }
+}
+
+impl fmt::Debug for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Decoder::Identity => "Identity",
+            Decoder::Gzip(_) => "Gzip",
+            Decoder::Deflate(_) => "Deflate",
+            Decoder::Brotli(_) => "Brotli",
+        };
+        f.write_str(name)
+    }
+}
+
+fn corrupt(err: io::Error) -> Error {
+    Error::Http(format!("Couldn't decompress response body: {}", err).into())
+}
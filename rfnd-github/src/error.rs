@@ -0,0 +1,267 @@
+use crate::graphql::GraphqlError;
+use crate::rate_limit::RateLimit;
+use crate::timeout::Timeout;
+use hyper::StatusCode;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::str::Utf8Error;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Everything that can go wrong while fetching something from the GitHub API.
+#[derive(Debug)]
+pub enum Error {
+    /// The server couldn't be reached.
+    Connect(BoxError),
+    /// The TLS connector couldn't be created or the TLS handshake failed.
+    Tls(BoxError),
+    /// The request couldn't be sent or the response couldn't be read.
+    Http(BoxError),
+    /// GitHub refused the request, because the rate limit is exhausted. Contains the body.
+    RateLimited(RateLimit, String),
+    /// The server answered with a 4xx status. Contains the status and the body.
+    ClientStatus(u16, String),
+    /// The server answered with a 5xx status. Contains the status and the body.
+    ServerStatus(u16, String),
+    /// The response body isn't valid UTF-8.
+    Utf8(Utf8Error),
+    /// The response body isn't the JSON we expected.
+    Json {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A cached response couldn't be read.
+    Cache(io::Error),
+    /// The server took too long, see `TimeoutOptions`.
+    Timeout(Timeout),
+    /// A response couldn't be recorded or there is no recorded response for a request, see
+    /// `Fixtures`.
+    Fixture(String),
+    /// A response body couldn't be written to a file.
+    Output(PathBuf, io::Error),
+    /// A GraphQL query failed, see `GraphqlResponse::into_data`.
+    Graphql(Vec<GraphqlError>),
+    /// The server redirected the request, but the redirect couldn't be followed, e.g. because
+    /// it led into a loop.
+    Redirect(String),
+}
+
+impl Error {
+    /// Turns 4xx and 5xx responses and redirects which weren't followed into the matching error.
+    /// GitHub answers with `403` (and sometimes `429`) when the rate limit is exhausted, which is
+    /// told apart from a `403` caused by missing permissions with the help of the
+    /// `X-RateLimit-*` headers.
+    pub fn check_status(
+        status: StatusCode,
+        rate_limit: Option<RateLimit>,
+        body: &str,
+    ) -> Result<(), Error> {
+        match rate_limit {
+            Some(rate_limit) if rate_limit.refused(status) => {
+                Err(Error::RateLimited(rate_limit, body.to_string()))
+            }
+            _ if status.is_redirection() && status != StatusCode::NOT_MODIFIED => Err(
+                Error::Redirect(format!("unexpected status {}", status.as_u16())),
+            ),
+            _ if status.is_client_error() => {
+                Err(Error::ClientStatus(status.as_u16(), body.to_string()))
+            }
+            _ if status.is_server_error() => {
+                Err(Error::ServerStatus(status.as_u16(), body.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// A JSON error at the given position in the whole document, which isn't necessarily the
+    /// position serde_json saw.
+    pub(crate) fn json_at(err: serde_json::Error, line: usize, column: usize) -> Self {
+        // serde_json appends the position to its message, but we report it separately
+        let position = format!(" at line {} column {}", err.line(), err.column());
+        let message = err.to_string();
+
+        Error::Json {
+            line,
+            column,
+            message: message.trim_end_matches(&position).to_string(),
+        }
+    }
+
+    /// The process exit code for this error, so scripts can branch on the kind of failure.
+    /// (`2` is used for invalid command line arguments.)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Connect(_) => 3,
+            Error::Tls(_) => 4,
+            Error::Http(_) => 5,
+            Error::ClientStatus(_, _) => 6,
+            Error::ServerStatus(_, _) => 7,
+            Error::Utf8(_) => 8,
+            Error::Json { .. } => 9,
+            Error::RateLimited(_, _) => 10,
+            Error::Cache(_) => 11,
+            Error::Timeout(_) => 12,
+            Error::Fixture(_) => 13,
+            Error::Output(_, _) => 14,
+            Error::Graphql(_) => 15,
+            Error::Redirect(_) => 16,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Connect(err) => write!(f, "Couldn't connect: {}", Chain(err.as_ref())),
+            Error::Tls(err) => write!(f, "TLS error: {}", Chain(err.as_ref())),
+            Error::Http(err) => {
+                write!(f, "Couldn't send request: {}", Chain(err.as_ref()))
+            }
+            Error::RateLimited(rate_limit, body) => {
+                write!(f, "Rate limit exceeded ({})\n{}", rate_limit, body)
+            }
+            Error::ClientStatus(status, body) => {
+                write!(f, "Got client error: {}\n{}", status, body)
+            }
+            Error::ServerStatus(status, body) => {
+                write!(f, "Got server error: {}\n{}", status, body)
+            }
+            Error::Utf8(err) => write!(f, "Response isn't valid UTF-8: {}", err),
+            Error::Cache(err) => write!(f, "Couldn't read cached response: {}", err),
+            Error::Timeout(timeout) => write!(f, "Timed out: {}", timeout),
+            Error::Fixture(message) => write!(f, "{}", message),
+            Error::Redirect(message) => write!(f, "Couldn't follow redirect: {}", message),
+            Error::Output(path, err) => write!(f, "Couldn't write {}: {}", path.display(), err),
+            Error::Graphql(errors) if errors.is_empty() => {
+                write!(f, "GraphQL query returned no data")
+            }
+            Error::Graphql(errors) => {
+                write!(f, "GraphQL query failed:")?;
+                for err in errors {
+                    write!(f, "\n{}", err)?;
+                }
+                Ok(())
+            }
+            Error::Json {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Couldn't parse JSON at line {}, column {}: {}",
+                line, column, message
+            ),
+        }
+    }
+}
+
+// hyper's errors are rather terse on their own, the interesting part is usually in their causes
+struct Chain<'a>(&'a (dyn StdError + 'static));
+
+impl fmt::Display for Chain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut last = self.0.to_string();
+        write!(f, "{}", last)?;
+
+        let mut cause = self.0.source();
+        while let Some(err) = cause {
+            // some errors repeat the message of their cause
+            let message = err.to_string();
+            if message != last {
+                write!(f, ": {}", message)?;
+            }
+            last = message;
+            cause = err.source();
+        }
+        Ok(())
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Connect(err) | Error::Tls(err) | Error::Http(err) => Some(err.as_ref()),
+            Error::Utf8(err) => Some(err),
+            Error::Cache(err) | Error::Output(_, err) => Some(err),
+            Error::Timeout(timeout) => Some(timeout),
+            _ => None,
+        }
+    }
+}
+
+impl From<hyper_util::client::legacy::Error> for Error {
+    fn from(err: hyper_util::client::legacy::Error) -> Self {
+        if let Some(timeout) = find_timeout(&err) {
+            Error::Timeout(timeout)
+        } else if is_tls_error(&err) {
+            Error::Tls(Box::new(err))
+        } else if err.is_connect() {
+            Error::Connect(Box::new(err))
+        } else {
+            Error::Http(Box::new(err))
+        }
+    }
+}
+
+impl From<hyper::Error> for Error {
+    fn from(err: hyper::Error) -> Self {
+        match find_timeout(&err) {
+            Some(timeout) => Error::Timeout(timeout),
+            None => Error::Http(Box::new(err)),
+        }
+    }
+}
+
+impl From<native_tls::Error> for Error {
+    fn from(err: native_tls::Error) -> Self {
+        Error::Tls(Box::new(err))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        let (line, column) = (err.line(), err.column());
+        Error::json_at(err, line, column)
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(err: Utf8Error) -> Self {
+        Error::Utf8(err)
+    }
+}
+
+// hyper-tls passes handshake failures through as the cause of the connect error
+fn is_tls_error(err: &(dyn StdError + 'static)) -> bool {
+    let mut cause = Some(err);
+    while let Some(err) = cause {
+        if err.is::<native_tls::Error>() {
+            return true;
+        }
+        cause = err.source();
+    }
+    false
+}
+
+// our timeouts end up somewhere in the causes of hyper's errors
+fn find_timeout(err: &(dyn StdError + 'static)) -> Option<Timeout> {
+    let mut cause = Some(err);
+    while let Some(err) = cause {
+        // `io::Error` doesn't return the error it wraps from `source()`
+        let err = match err
+            .downcast_ref::<io::Error>()
+            .and_then(|err| err.get_ref())
+        {
+            Some(inner) => inner,
+            None => err,
+        };
+        if let Some(timeout) = err.downcast_ref::<Timeout>() {
+            return Some(*timeout);
+        }
+        cause = err.source();
+    }
+    None
+}
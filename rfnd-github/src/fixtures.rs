@@ -0,0 +1,250 @@
+use crate::body::RequestBody;
+use crate::cache::{self, header_map, header_pairs};
+use crate::client::HttpsClient;
+use crate::error::Error;
+use crate::trace::{Event, Tracer};
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use hyper::body::Incoming;
+use hyper::{Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// Options to record the exchanges with the server or to replay them later, e.g. in a CI
+/// without network access. These are command line arguments with the `clap` feature.
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+#[derive(Debug, Clone, Default)]
+pub struct FixtureOptions {
+    /// Save every response to this directory, so it can be replayed with --replay
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "DIR", conflicts_with = "replay")
+    )]
+    pub record: Option<PathBuf>,
+
+    /// Answer requests with the responses saved by --record instead of using the network
+    #[cfg_attr(feature = "clap", arg(long, value_name = "DIR"))]
+    pub replay: Option<PathBuf>,
+}
+
+impl FixtureOptions {
+    pub fn fixtures(&self) -> Option<Fixtures> {
+        match (&self.record, &self.replay) {
+            (Some(dir), _) => Some(Fixtures::Record(dir.clone())),
+            (None, Some(dir)) => Some(Fixtures::Replay(dir.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+/// A directory of recorded responses.
+///
+/// Responses are matched by method, path, query and request body, so they can be replayed
+/// against another base URL and without the access token they were recorded with.
+#[derive(Debug, Clone)]
+pub enum Fixtures {
+    /// Send requests over the network and save every response, replacing older ones.
+    Record(PathBuf),
+    /// Never touch the network, fail for requests which weren't recorded.
+    Replay(PathBuf),
+}
+
+// the meta data of a recorded response, the body is stored next to it
+#[derive(Serialize, Deserialize, Debug)]
+struct Exchange {
+    method: String,
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+/// Sends requests either over the network or to the fixtures.
+#[derive(Debug, Clone)]
+pub(crate) struct Transport {
+    http: HttpsClient,
+    fixtures: Option<Fixtures>,
+    tracer: Tracer,
+}
+
+/// The body of a response from a `Transport`.
+#[derive(Debug)]
+pub(crate) enum ResponseBody {
+    Network(Incoming),
+    Recorded(File),
+}
+
+impl ResponseBody {
+    /// Returns the next chunk of the body or `None` at its end.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, Error> {
+        match self {
+            ResponseBody::Recorded(file) => cache::read_chunk(file)
+                .map_err(|err| Error::Fixture(format!("Couldn't read recorded body: {}", err))),
+            ResponseBody::Network(body) => loop {
+                let frame = match body.frame().await {
+                    Some(frame) => frame?,
+                    None => return Ok(None),
+                };
+                // skip trailers
+                if let Ok(data) = frame.into_data() {
+                    return Ok(Some(data));
+                }
+            },
+        }
+    }
+}
+
+impl Transport {
+    pub fn new(http: HttpsClient, fixtures: Option<Fixtures>, tracer: Tracer) -> Self {
+        Transport {
+            http,
+            fixtures,
+            tracer,
+        }
+    }
+
+    pub async fn request(
+        &self,
+        req: Request<RequestBody>,
+    ) -> Result<Response<ResponseBody>, Error> {
+        self.tracer.emit(Event::Request {
+            method: req.method(),
+            uri: req.uri(),
+            headers: req.headers(),
+        });
+
+        let res = self.send(req).await?;
+        self.tracer.emit(Event::Response {
+            version: res.version(),
+            status: res.status(),
+            headers: res.headers(),
+        });
+        Ok(res)
+    }
+
+    async fn send(&self, req: Request<RequestBody>) -> Result<Response<ResponseBody>, Error> {
+        let fixtures = match &self.fixtures {
+            None => return Ok(self.http.request(req).await?.map(ResponseBody::Network)),
+            Some(fixtures) => fixtures,
+        };
+
+        // the body is part of the key, so e.g. every GraphQL query gets a response of its own.
+        // A streamed body is read completely for that, it is sent only once anyway.
+        let (method, url) = (req.method().clone(), url(&req));
+        let (parts, body) = req.into_parts();
+        let body = body.collect().await.map_err(|err| {
+            Error::Fixture(format!("Couldn't read body of {} {}: {}", method, url, err))
+        })?;
+        let body = body.to_bytes();
+        let key = Key {
+            method: &method,
+            url: &url,
+            body: &body,
+        };
+
+        let dir = match fixtures {
+            Fixtures::Replay(dir) => return replay(dir, &key),
+            Fixtures::Record(dir) => dir,
+        };
+        let req = Request::from_parts(parts, RequestBody::bytes(body.clone()));
+        let res = self.http.request(req).await?;
+        // the whole body is recorded upfront, so the fixture is complete even if the caller
+        // stops reading early
+        let (parts, body) = res.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        let exchange = Exchange {
+            method: method.to_string(),
+            url: url.clone(),
+            status: parts.status.as_u16(),
+            headers: header_pairs(&parts.headers),
+        };
+        let name = key.file_name();
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(dir.join(format!("{}.body", name)), &body))
+            .and_then(|_| Ok(serde_json::to_vec_pretty(&exchange)?))
+            .and_then(|meta| fs::write(dir.join(format!("{}.json", name)), meta))
+            .map_err(|err| {
+                Error::Fixture(format!("Couldn't record {} {}: {}", method, url, err))
+            })?;
+
+        replay(dir, &key)
+    }
+}
+
+// what a recorded response is found by
+struct Key<'a> {
+    method: &'a Method,
+    url: &'a str,
+    body: &'a [u8],
+}
+
+fn replay(dir: &Path, key: &Key) -> Result<Response<ResponseBody>, Error> {
+    let (method, url) = (key.method, key.url);
+    let name = key.file_name();
+    let meta = fs::read(dir.join(format!("{}.json", name))).map_err(|_| {
+        Error::Fixture(format!(
+            "No response recorded for {} {} in {}",
+            method,
+            url,
+            dir.display()
+        ))
+    })?;
+
+    let broken = |err: &dyn std::fmt::Display| {
+        Error::Fixture(format!(
+            "Broken recording of {} {} in {}: {}",
+            method,
+            url,
+            dir.display(),
+            err
+        ))
+    };
+    let exchange: Exchange = serde_json::from_slice(&meta).map_err(|err| broken(&err))?;
+    let status = StatusCode::from_u16(exchange.status).map_err(|err| broken(&err))?;
+    let body = File::open(dir.join(format!("{}.body", name))).map_err(|err| broken(&err))?;
+
+    let mut res = Response::new(ResponseBody::Recorded(body));
+    *res.status_mut() = status;
+    *res.headers_mut() = header_map(&exchange.headers);
+    Ok(res)
+}
+
+// the host isn't part of it, so the fixtures work with any base URL
+fn url<B>(req: &Request<B>) -> String {
+    req.uri()
+        .path_and_query()
+        .map(|path| path.to_string())
+        .unwrap_or_else(|| "/".to_string())
+}
+
+impl Key<'_> {
+    // readable enough to find a fixture by its name, the hash keeps similar URLs and requests
+    // with different bodies apart
+    fn file_name(&self) -> String {
+        let request = format!("{} {}", self.method, self.url);
+        let readable: String = request
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .take(80)
+            .collect();
+        let mut hash = Sha256::new();
+        hash.update(&request);
+        // requests without a body keep the names they were recorded with before bodies counted
+        if !self.body.is_empty() {
+            hash.update(b"\n");
+            hash.update(self.body);
+        }
+
+        format!(
+            "{}-{}",
+            readable.trim_end_matches('_'),
+            hash.finalize()
+                .iter()
+                .take(4)
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        )
+    }
+}
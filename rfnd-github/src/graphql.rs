@@ -0,0 +1,76 @@
+use crate::error::Error;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+
+/// The response to a query sent with `GithubClient::graphql`.
+///
+/// GraphQL reports most failures (e.g. an unknown login) with a `200` and a list of errors,
+/// possibly next to partial data.
+#[derive(Deserialize, Debug)]
+pub struct GraphqlResponse<T> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub errors: Vec<GraphqlError>,
+}
+
+impl<T> GraphqlResponse<T> {
+    /// The data, if the query didn't fail at all.
+    pub fn into_data(self) -> Result<T, Error> {
+        match self.data {
+            Some(data) if self.errors.is_empty() => Ok(data),
+            _ => Err(Error::Graphql(self.errors)),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GraphqlError {
+    pub message: String,
+    /// GitHub's kind of error, e.g. `NOT_FOUND`.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Where in the query the error happened, made of field names and list indices.
+    #[serde(default)]
+    pub path: Vec<Value>,
+}
+
+impl fmt::Display for GraphqlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path: Vec<String> = self
+            .path
+            .iter()
+            .map(|segment| match segment {
+                Value::String(field) => field.clone(),
+                index => index.to_string(),
+            })
+            .collect();
+        match path.is_empty() {
+            true => write!(f, "{}", self.message),
+            false => write!(f, "{} (at {})", self.message, path.join(".")),
+        }
+    }
+}
+
+/// One page of a list, which GraphQL calls a connection. The query has to select its `nodes`
+/// and its `pageInfo { hasNextPage endCursor }`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection<T> {
+    pub nodes: Vec<T>,
+    pub page_info: PageInfo,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+
+impl PageInfo {
+    /// The cursor to pass as `after` to get the next page, if there is one.
+    pub fn next_cursor(&self) -> Option<&str> {
+        self.end_cursor.as_deref().filter(|_| self.has_next_page)
+    }
+}
@@ -0,0 +1,254 @@
+use crate::error::Error;
+use serde::de::DeserializeOwned;
+use std::str::from_utf8;
+
+/// Splits a JSON array which arrives in chunks into its elements, so every element can be
+/// deserialized as soon as it is complete. Only the element which is currently parsed is buffered.
+#[derive(Debug)]
+pub struct ArrayParser {
+    state: State,
+    element: Vec<u8>,
+    // nesting of objects and arrays inside the current element
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    // position of the next byte in the whole document, to report errors like serde_json does
+    line: usize,
+    column: usize,
+    element_line: usize,
+    element_column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    BeforeArray,
+    BeforeElement { first: bool },
+    InElement,
+    AfterElement,
+    Done,
+}
+
+/// The raw bytes of one element of the array.
+#[derive(Debug)]
+pub struct Element {
+    bytes: Vec<u8>,
+    line: usize,
+    column: usize,
+}
+
+impl ArrayParser {
+    pub fn new() -> Self {
+        ArrayParser {
+            state: State::BeforeArray,
+            element: Vec::new(),
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            line: 1,
+            column: 1,
+            element_line: 1,
+            element_column: 1,
+        }
+    }
+
+    /// Adds the next chunk of the document and returns all elements which are complete now.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<Element>, Error> {
+        let mut elements = Vec::new();
+
+        for &byte in chunk {
+            match self.state {
+                State::BeforeArray => match byte {
+                    b'[' => self.state = State::BeforeElement { first: true },
+                    _ if byte.is_ascii_whitespace() => {}
+                    _ => return Err(self.error("expected an array")),
+                },
+                State::BeforeElement { first } => match byte {
+                    b']' if first => self.state = State::Done,
+                    b',' | b']' => return Err(self.error("expected value")),
+                    _ if byte.is_ascii_whitespace() => {}
+                    _ => {
+                        self.state = State::InElement;
+                        self.element_line = self.line;
+                        self.element_column = self.column;
+                        self.push_element_byte(byte, &mut elements);
+                    }
+                },
+                State::InElement => self.push_element_byte(byte, &mut elements),
+                State::AfterElement => match byte {
+                    b',' => self.state = State::BeforeElement { first: false },
+                    b']' => self.state = State::Done,
+                    _ if byte.is_ascii_whitespace() => {}
+                    _ => return Err(self.error("expected `,` or `]`")),
+                },
+                State::Done => {
+                    if !byte.is_ascii_whitespace() {
+                        return Err(self.error("trailing characters"));
+                    }
+                }
+            }
+
+            if byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
+        Ok(elements)
+    }
+
+    /// Checks that the document ended with the end of the array.
+    pub fn finish(&self) -> Result<(), Error> {
+        match self.state {
+            State::Done => Ok(()),
+            _ => Err(self.error("EOF while parsing a list")),
+        }
+    }
+
+    fn push_element_byte(&mut self, byte: u8, elements: &mut Vec<Element>) {
+        if self.in_string {
+            self.element.push(byte);
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+                if self.depth == 0 {
+                    self.complete_element(State::AfterElement, elements);
+                }
+            }
+            return;
+        }
+
+        match byte {
+            b'"' => self.in_string = true,
+            b'{' | b'[' => self.depth += 1,
+            b'}' | b']' if self.depth > 0 => self.depth -= 1,
+            // the end of a number, `true`, `false` or `null`
+            b']' => return self.complete_element(State::Done, elements),
+            b',' if self.depth == 0 => {
+                return self.complete_element(State::BeforeElement { first: false }, elements)
+            }
+            _ if byte.is_ascii_whitespace() && self.depth == 0 => {
+                return self.complete_element(State::AfterElement, elements)
+            }
+            _ => {}
+        }
+
+        self.element.push(byte);
+        if self.depth == 0 && (byte == b'}' || byte == b']') {
+            self.complete_element(State::AfterElement, elements);
+        }
+    }
+
+    fn complete_element(&mut self, next: State, elements: &mut Vec<Element>) {
+        elements.push(Element {
+            bytes: std::mem::take(&mut self.element),
+            line: self.element_line,
+            column: self.element_column,
+        });
+        self.state = next;
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Json {
+            line: self.line,
+            column: self.column,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Element {
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        let json = from_utf8(&self.bytes)?;
+        serde_json::from_str(json).map_err(|err| self.json_error(err))
+    }
+
+    // serde_json only knows the position inside of the element
+    fn json_error(&self, err: serde_json::Error) -> Error {
+        let (line, column) = if err.line() <= 1 {
+            (self.line, self.column + err.column().saturating_sub(1))
+        } else {
+            (self.line + err.line() - 1, err.column())
+        };
+
+        Error::json_at(err, line, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    // parses the document in chunks of `size` bytes
+    fn parse(document: &str, size: usize) -> Result<Vec<Value>, Error> {
+        let mut parser = ArrayParser::new();
+        let mut values = Vec::new();
+        for chunk in document.as_bytes().chunks(size) {
+            for element in parser.push(chunk)? {
+                values.push(element.deserialize()?);
+            }
+        }
+        parser.finish()?;
+        Ok(values)
+    }
+
+    #[test]
+    fn splits_at_every_chunk_boundary() {
+        let document = r#"[{"name": "a,b]", "topics": ["x", "\"}"]}, {"escaped": "\\"}]"#;
+        let expected = [
+            json!({"name": "a,b]", "topics": ["x", "\"}"]}),
+            json!({"escaped": "\\"}),
+        ];
+        for size in 1..=document.len() {
+            assert_eq!(
+                parse(document, size).unwrap(),
+                expected,
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn parses_scalar_elements() {
+        let document = "[1, \"two\",true ,null,[],{}, -3.5e2]";
+        let expected = [
+            json!(1),
+            json!("two"),
+            json!(true),
+            json!(null),
+            json!([]),
+            json!({}),
+            json!(-350.0),
+        ];
+        for size in 1..=document.len() {
+            assert_eq!(
+                parse(document, size).unwrap(),
+                expected,
+                "chunk size {}",
+                size
+            );
+        }
+        assert_eq!(parse(" [ ] \n", 1).unwrap(), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn reports_position_of_errors() {
+        match parse("[1,\n  2,]", 4) {
+            Err(Error::Json { line, column, .. }) => assert_eq!((line, column), (2, 5)),
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse("[{\"a\": 1},\n {\"b\": x}]", 3) {
+            Err(Error::Json { line, column, .. }) => assert_eq!((line, column), (2, 8)),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(parse("[1, 2", 2).is_err());
+        assert!(parse("{}", 2).is_err());
+        assert!(parse("[] []", 2).is_err());
+    }
+}
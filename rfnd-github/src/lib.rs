@@ -0,0 +1,51 @@
+//! A small client for the GitHub REST and GraphQL APIs, shared by the `http-requests` and
+//! `parse-json` examples.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), rfnd_github::Error> {
+//! let client = rfnd_github::GithubClient::new()?;
+//! for repository in client.repos("donaldpipowitch").await? {
+//!     println!("{}", repository.name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The `clap` feature makes the option structs usable as `#[command(flatten)]` arguments and
+//! adds the `cli` module for what the command line programs share beyond that.
+
+mod auth;
+mod body;
+mod cache;
+#[cfg(feature = "clap")]
+pub mod cli;
+mod client;
+mod encoding;
+mod error;
+mod fixtures;
+mod graphql;
+mod json_stream;
+mod proxy;
+mod rate_limit;
+mod repository;
+mod retry;
+mod timeout;
+mod tls;
+mod trace;
+mod user;
+
+pub use crate::auth::Token;
+pub use crate::body::RequestBody;
+pub use crate::cache::{Cache, CacheCommand, CacheOptions, FetchedResponse};
+pub use crate::client::{parse_base_url, GithubClient, GithubClientBuilder, Page};
+pub use crate::encoding::BodySize;
+pub use crate::error::Error;
+pub use crate::fixtures::{FixtureOptions, Fixtures};
+pub use crate::graphql::{Connection, GraphqlError, GraphqlResponse, PageInfo};
+pub use crate::rate_limit::RateLimit;
+pub use crate::repository::{License, Owner, OwnerKind, Repository, Visibility};
+pub use crate::retry::RetryPolicy;
+pub use crate::timeout::{Deadline, Timeout, TimeoutOptions};
+pub use crate::tls::{Certificates, Pem, TlsOptions};
+pub use crate::trace::Event;
+pub use crate::user::User;
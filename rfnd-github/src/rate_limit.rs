@@ -0,0 +1,64 @@
+use hyper::header::HeaderMap;
+use hyper::StatusCode;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// GitHub's rate limit as reported by the `X-RateLimit-*` headers of a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// How many requests are allowed per hour.
+    pub limit: u64,
+    /// How many requests are left in the current window.
+    pub remaining: u64,
+    /// When the current window ends and `remaining` is set back to `limit`.
+    pub reset: SystemTime,
+}
+
+impl RateLimit {
+    /// Returns `None` if the headers are missing or malformed, e.g. because the server isn't GitHub.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| -> Option<u64> {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+        };
+
+        Some(RateLimit {
+            limit: header("x-ratelimit-limit")?,
+            remaining: header("x-ratelimit-remaining")?,
+            reset: SystemTime::UNIX_EPOCH + Duration::from_secs(header("x-ratelimit-reset")?),
+        })
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Whether a response with this status was refused because of the rate limit. GitHub sends
+    /// the headers with every response, so e.g. a `404` may have used up the last request, too.
+    pub(crate) fn refused(&self, status: StatusCode) -> bool {
+        let is_rate_limit_status =
+            status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS;
+        is_rate_limit_status && self.is_exhausted()
+    }
+
+    /// How long it takes until the rate limit resets. Zero if it should have been reset already.
+    pub fn reset_in(&self) -> Duration {
+        self.reset
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::from_secs(0))
+    }
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} requests remaining, resets in {}s",
+            self.remaining,
+            self.limit,
+            self.reset_in().as_secs()
+        )
+    }
+}
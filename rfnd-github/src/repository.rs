@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A repository as returned by `GET /users/:user/repos`.
+///
+/// Only the fields we care about are typed. Everything else ends up in `extra`, so serializing a
+/// `Repository` again gives back all the data GitHub sent.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Repository {
+    pub id: u64,
+    pub name: String,
+    pub full_name: String,
+    pub html_url: String,
+    pub description: Option<String>,
+    pub fork: bool,
+    pub owner: Owner,
+    pub license: Option<License>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    pub stargazers_count: u64,
+    pub language: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// `null` for repositories nobody ever pushed to.
+    pub pushed_at: Option<DateTime<Utc>>,
+    pub archived: bool,
+    pub visibility: Visibility,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Owner {
+    pub login: String,
+    pub id: u64,
+    pub html_url: String,
+    #[serde(rename = "type")]
+    pub kind: OwnerKind,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnerKind {
+    User,
+    Organization,
+    Bot,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct License {
+    pub key: String,
+    pub name: String,
+    /// `null` for licenses GitHub couldn't identify ("NOASSERTION" is used as well).
+    pub spdx_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    Private,
+    /// Only visible to members of the enterprise (GitHub Enterprise).
+    Internal,
+}
+
+impl Visibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Private => "private",
+            Visibility::Internal => "internal",
+        }
+    }
+}
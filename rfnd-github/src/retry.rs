@@ -0,0 +1,180 @@
+use crate::body::RequestBody;
+use crate::error::Error;
+use crate::fixtures::{ResponseBody, Transport};
+use crate::rate_limit::RateLimit;
+use crate::timeout::Timeout;
+use hyper::header::{HeaderMap, RETRY_AFTER};
+use hyper::{Method, Request, Response, StatusCode};
+use std::time::{Duration, SystemTime};
+use tokio::time::sleep;
+
+/// How often and how patiently failed requests are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// How often a request is sent at most, including the first attempt.
+    pub max_attempts: u32,
+    /// The delay before the first retry. It doubles with every further retry.
+    pub base_delay: Duration,
+    /// The upper bound for the computed delays. Delays requested by the server aren't capped.
+    pub max_delay: Duration,
+    /// Whether to sleep until GitHub's rate limit resets instead of failing. This doesn't count
+    /// as an attempt, but happens at most once per request.
+    pub wait_for_rate_limit: bool,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            wait_for_rate_limit: false,
+        }
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry - 1))
+            .min(self.max_delay);
+
+        // keep half of the delay and randomize the other half, so parallel clients don't retry in lockstep
+        let half = delay / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
+
+/// Sends the request created by `build` and sends it again on connection errors, `429` and
+/// `5xx` gateway errors. The last response is returned as it is, even if it is an error.
+/// Optionally waits for an exhausted rate limit to reset, see `RetryPolicy::wait_for_rate_limit`.
+pub(crate) async fn send<F>(
+    transport: &Transport,
+    policy: RetryPolicy,
+    build: F,
+) -> Result<Response<ResponseBody>, Error>
+where
+    F: Fn() -> Request<RequestBody>,
+{
+    let mut attempt = 1;
+    let mut waited_for_rate_limit = false;
+
+    loop {
+        let req = build();
+        let method = req.method().clone();
+        let uri = req.uri().clone();
+
+        let result = transport.request(req).await;
+
+        let exhausted_rate_limit = match &result {
+            Ok(res) => RateLimit::from_headers(res.headers())
+                .filter(|rate_limit| rate_limit.refused(res.status())),
+            _ => None,
+        };
+
+        // nothing but waiting helps, which only happens if the caller asked for it - otherwise
+        // the response becomes `Error::RateLimited` like a `403` does
+        if let Some(rate_limit) = exhausted_rate_limit {
+            if policy.wait_for_rate_limit && !waited_for_rate_limit {
+                // don't hammer the server if our clock is a little bit behind
+                let delay = rate_limit.reset_in().max(Duration::from_secs(1));
+                eprintln!(
+                    "Rate limit exceeded for {}, waiting {}s for it to reset",
+                    uri,
+                    delay.as_secs()
+                );
+                sleep(delay).await;
+                waited_for_rate_limit = true;
+                continue;
+            }
+            return result;
+        }
+
+        if attempt >= policy.max_attempts {
+            return result;
+        }
+
+        let (reason, delay) = match &result {
+            Ok(res) if is_retryable(&method, res.status()) => (
+                format!("status {}", res.status().as_u16()),
+                requested_delay(res.headers()).unwrap_or_else(|| policy.backoff(attempt)),
+            ),
+            Err(Error::Connect(_)) => ("a connection error".to_string(), policy.backoff(attempt)),
+            Err(Error::Timeout(Timeout::Connect(_))) => {
+                ("a connection timeout".to_string(), policy.backoff(attempt))
+            }
+            _ => return result,
+        };
+
+        eprintln!(
+            "Request to {} failed with {}, retrying in {:.1}s (attempt {} of {})",
+            uri,
+            reason,
+            delay.as_secs_f64(),
+            attempt + 1,
+            policy.max_attempts
+        );
+        sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+fn is_retryable(method: &Method, status: StatusCode) -> bool {
+    match status {
+        // the server didn't process the request, so it is safe to send it again
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => true,
+        StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => method.is_idempotent(),
+        _ => false,
+    }
+}
+
+// `Retry-After` is either a number of seconds or a date
+fn requested_delay(headers: &HeaderMap) -> Option<Duration> {
+    let retry_after = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = retry_after.trim().parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(retry_after).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::from_secs(0)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retry_after(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn reads_delay_in_seconds() {
+        assert_eq!(
+            requested_delay(&retry_after("120")),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(requested_delay(&HeaderMap::new()), None);
+        assert_eq!(requested_delay(&retry_after("soon")), None);
+    }
+
+    #[test]
+    fn reads_delay_until_date() {
+        // HTTP dates have no fractions of a second
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(90));
+        let delay = requested_delay(&retry_after(&date)).unwrap();
+        assert!(
+            delay > Duration::from_secs(88) && delay <= Duration::from_secs(90),
+            "{:?}",
+            delay
+        );
+    }
+
+    #[test]
+    fn reads_past_date_as_no_delay() {
+        let delay = requested_delay(&retry_after("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(delay, Some(Duration::from_secs(0)));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::error::Error;
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper::Uri;
+use hyper_util::client::legacy::connect::{Connected, Connection};
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{sleep, timeout_at, Instant, Sleep};
+use tower_service::Service;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Timeouts, so a stalled server doesn't hang us forever. These are command line arguments with
+/// the `clap` feature.
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+#[derive(Debug, Clone)]
+pub struct TimeoutOptions {
+    /// Give up connecting (including proxy tunnel and TLS handshake) after this many seconds
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "SECONDS",
+            default_value = "10",
+            env = "RFND_CONNECT_TIMEOUT",
+            value_parser = parse_seconds
+        )
+    )]
+    pub connect_timeout: Duration,
+
+    /// Give up if the server sends nothing for this many seconds
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "SECONDS",
+            default_value = "30",
+            env = "RFND_READ_TIMEOUT",
+            value_parser = parse_seconds
+        )
+    )]
+    pub read_timeout: Duration,
+
+    /// Give up if everything together (including retries) takes longer than this many seconds
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "SECONDS", env = "RFND_TIMEOUT", value_parser = parse_seconds)
+    )]
+    pub timeout: Option<Duration>,
+}
+
+impl TimeoutOptions {
+    /// Starts the clock for `--timeout`.
+    pub fn deadline(&self) -> Deadline {
+        Deadline(
+            self.timeout
+                .map(|timeout| (Instant::now() + timeout, timeout)),
+        )
+    }
+}
+
+impl Default for TimeoutOptions {
+    fn default() -> Self {
+        TimeoutOptions {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            timeout: None,
+        }
+    }
+}
+
+#[cfg(feature = "clap")]
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    match value.parse::<f64>().map(Duration::try_from_secs_f64) {
+        Ok(Ok(duration)) if !duration.is_zero() => Ok(duration),
+        _ => Err("expected a positive number of seconds".to_string()),
+    }
+}
+
+/// Which timeout expired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    Connect(Duration),
+    Read(Duration),
+    Total(Duration),
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timeout::Connect(timeout) => {
+                write!(f, "couldn't connect within {}s", timeout.as_secs_f64())
+            }
+            Timeout::Read(timeout) => {
+                write!(f, "the server sent nothing for {}s", timeout.as_secs_f64())
+            }
+            Timeout::Total(timeout) => {
+                write!(f, "didn't finish within {}s", timeout.as_secs_f64())
+            }
+        }
+    }
+}
+
+impl StdError for Timeout {}
+
+/// The point in time at which `--timeout` expires, if it is set.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline(Option<(Instant, Duration)>);
+
+impl Deadline {
+    pub async fn run<T, F>(self, future: F) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        match self.0 {
+            Some((deadline, timeout)) => timeout_at(deadline, future)
+                .await
+                .unwrap_or(Err(Error::Timeout(Timeout::Total(timeout)))),
+            None => future.await,
+        }
+    }
+}
+
+/// Wraps a connector to limit how long connecting takes and how long a connection may stall
+/// while we wait for the server.
+#[derive(Clone)]
+pub struct TimeoutConnector<C> {
+    inner: C,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+}
+
+impl<C> TimeoutConnector<C> {
+    pub fn new(inner: C, options: &TimeoutOptions) -> Self {
+        TimeoutConnector {
+            inner,
+            connect_timeout: options.connect_timeout,
+            read_timeout: options.read_timeout,
+        }
+    }
+}
+
+impl<C> Service<Uri> for TimeoutConnector<C>
+where
+    C: Service<Uri>,
+    C::Response: Read + Write + Connection + Unpin + Send + 'static,
+    C::Future: Send + 'static,
+    C::Error: Into<BoxError>,
+{
+    type Response = TimeoutStream<C::Response>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let connecting = self.inner.call(dst);
+        let (connect_timeout, read_timeout) = (self.connect_timeout, self.read_timeout);
+
+        Box::pin(async move {
+            match tokio::time::timeout(connect_timeout, connecting).await {
+                Ok(Ok(stream)) => Ok(TimeoutStream {
+                    inner: stream,
+                    read_timeout,
+                    idle: None,
+                }),
+                Ok(Err(err)) => Err(err.into()),
+                Err(_) => Err(Timeout::Connect(connect_timeout).into()),
+            }
+        })
+    }
+}
+
+/// A connection which fails reading once the server didn't send anything for `read_timeout`.
+pub struct TimeoutStream<S> {
+    inner: S,
+    read_timeout: Duration,
+    // started by the first read which has to wait, reset whenever there is progress
+    idle: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S: Read + Unpin> Read for TimeoutStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: ReadBufCursor,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Poll::Ready(result) = Pin::new(&mut this.inner).poll_read(cx, buf) {
+            this.idle = None;
+            return Poll::Ready(result);
+        }
+
+        let read_timeout = this.read_timeout;
+        let idle = this
+            .idle
+            .get_or_insert_with(|| Box::pin(sleep(read_timeout)));
+        match idle.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                this.idle = None;
+                let err = io::Error::new(io::ErrorKind::TimedOut, Timeout::Read(read_timeout));
+                Poll::Ready(Err(err))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: Write + Unpin> Write for TimeoutStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        // a pooled connection may have been waiting for a long time before this request
+        if let (Poll::Ready(_), Some(idle)) = (&result, &mut this.idle) {
+            idle.as_mut().reset(Instant::now() + this.read_timeout);
+            // the pending read isn't necessarily polled again, so the timer has to wake us up
+            let _ = idle.as_mut().poll(cx);
+        }
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl<S: Connection> Connection for TimeoutStream<S> {
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
+}
@@ -0,0 +1,101 @@
+use crate::error::Error;
+use native_tls::{Certificate, Identity, TlsConnector};
+use std::fmt;
+use std::fs;
+
+/// Certificate options, mostly needed for GitHub Enterprise servers with their own CA. These are
+/// command line arguments with the `clap` feature.
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// Trust the CA certificates in this PEM file in addition to the system's
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "FILE", value_parser = Certificates::from_file)
+    )]
+    pub cacert: Option<Certificates>,
+
+    /// Authenticate with this PEM client certificate (mutual TLS)
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "FILE", requires = "key", value_parser = Pem::from_file)
+    )]
+    pub cert: Option<Pem>,
+
+    /// The PKCS #8 PEM private key of --cert
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "FILE", requires = "cert", value_parser = Pem::from_file)
+    )]
+    pub key: Option<Pem>,
+
+    /// Don't verify the server's certificate - only ever use this for testing
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub insecure: bool,
+}
+
+impl TlsOptions {
+    pub fn connector(&self) -> Result<TlsConnector, Error> {
+        let mut builder = TlsConnector::builder();
+
+        if let Some(Certificates(certificates)) = &self.cacert {
+            for certificate in certificates {
+                builder.add_root_certificate(certificate.clone());
+            }
+        }
+
+        if let (Some(cert), Some(key)) = (&self.cert, &self.key) {
+            builder.identity(Identity::from_pkcs8(&cert.0, &key.0)?);
+        }
+
+        if self.insecure {
+            eprintln!(
+                "WARNING: --insecure disables certificate verification. Anyone between you and \
+                 the server can read and change the traffic, including your access token!"
+            );
+            builder.danger_accept_invalid_certs(true);
+            builder.danger_accept_invalid_hostnames(true);
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// The certificates of a PEM bundle.
+#[derive(Clone)]
+pub struct Certificates(pub Vec<Certificate>);
+
+impl Certificates {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let pem = Pem::from_file(path)?;
+        match Certificate::stack_from_pem(&pem.0) {
+            Ok(certificates) if !certificates.is_empty() => Ok(Certificates(certificates)),
+            Ok(_) => Err("the file doesn't contain any certificates".to_string()),
+            Err(err) => Err(format!("Couldn't parse certificates: {}", err)),
+        }
+    }
+}
+
+impl fmt::Debug for Certificates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Certificates({})", self.0.len())
+    }
+}
+
+/// The content of a PEM file. Not printed in `Debug` output, because it may be a private key.
+#[derive(Clone)]
+pub struct Pem(pub Vec<u8>);
+
+impl Pem {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        fs::read(path)
+            .map(Pem)
+            .map_err(|err| format!("Couldn't read {}: {}", path, err))
+    }
+}
+
+impl fmt::Debug for Pem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Pem(***)")
+    }
+}
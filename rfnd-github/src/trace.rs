@@ -0,0 +1,153 @@
+use crate::proxy::ProxyConnector;
+use hyper::{HeaderMap, Method, StatusCode, Uri, Version};
+use hyper_tls::{HttpsConnector, MaybeHttpsStream};
+use hyper_util::client::legacy::connect::dns::{GaiResolver, Name};
+use hyper_util::rt::TokioIo;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::vec;
+use tokio::net::TcpStream;
+use tower_service::Service;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+type OnEvent = dyn Fn(&Event) + Send + Sync;
+
+/// Something that happened while a request was sent, see `GithubClientBuilder::trace`.
+///
+/// Connection events only show up for new connections. A request which is answered from the
+/// cache without asking the server has no events at all.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// A request is about to be sent. Retries are sent as requests of their own.
+    Request {
+        method: &'a Method,
+        uri: &'a Uri,
+        headers: &'a HeaderMap,
+    },
+    /// The host of a new connection was resolved.
+    Resolved {
+        host: &'a str,
+        addrs: &'a [SocketAddr],
+    },
+    /// A new connection was opened, to the proxy if one is used.
+    Connected { addr: SocketAddr },
+    /// The TLS handshake on a new connection is done.
+    TlsHandshake,
+    /// The status and headers of the response arrived, the body may still be on its way.
+    Response {
+        version: Version,
+        status: StatusCode,
+        headers: &'a HeaderMap,
+    },
+}
+
+/// Passes events to the callback given to `GithubClientBuilder::trace`, if there is one.
+#[derive(Clone, Default)]
+pub(crate) struct Tracer(Option<Arc<OnEvent>>);
+
+impl Tracer {
+    pub fn new<F>(on_event: F) -> Self
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        Tracer(Some(Arc::new(on_event)))
+    }
+
+    pub fn emit(&self, event: Event) {
+        if let Some(on_event) = &self.0 {
+            on_event(&event);
+        }
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("Tracer(enabled)"),
+            None => f.write_str("Tracer(disabled)"),
+        }
+    }
+}
+
+/// Resolves host names like hyper does by default and reports the result.
+#[derive(Clone)]
+pub struct TraceResolver {
+    inner: GaiResolver,
+    tracer: Tracer,
+}
+
+impl TraceResolver {
+    pub(crate) fn new(tracer: Tracer) -> Self {
+        TraceResolver {
+            inner: GaiResolver::new(),
+            tracer,
+        }
+    }
+}
+
+impl Service<Name> for TraceResolver {
+    type Response = vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let host = name.as_str().to_string();
+        let resolving = self.inner.call(name);
+        let tracer = self.tracer.clone();
+
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = resolving.await?.collect();
+            tracer.emit(Event::Resolved {
+                host: &host,
+                addrs: &addrs,
+            });
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+/// Reports finished TLS handshakes of the `HttpsConnector`.
+#[derive(Clone)]
+pub struct TraceConnector {
+    inner: HttpsConnector<ProxyConnector>,
+    tracer: Tracer,
+}
+
+impl TraceConnector {
+    pub(crate) fn new(inner: HttpsConnector<ProxyConnector>, tracer: Tracer) -> Self {
+        TraceConnector { inner, tracer }
+    }
+}
+
+impl Service<Uri> for TraceConnector {
+    type Response = MaybeHttpsStream<TokioIo<TcpStream>>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let connecting = self.inner.call(dst);
+        let tracer = self.tracer.clone();
+
+        Box::pin(async move {
+            let stream = connecting.await?;
+            if let MaybeHttpsStream::Https(_) = stream {
+                tracer.emit(Event::TlsHandshake);
+            }
+            Ok(stream)
+        })
+    }
+}
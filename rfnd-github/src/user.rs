@@ -0,0 +1,29 @@
+use crate::repository::OwnerKind;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A user as returned by `GET /users/:user`.
+///
+/// Like `Repository`, everything which isn't typed ends up in `extra`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct User {
+    pub login: String,
+    pub id: u64,
+    pub html_url: String,
+    #[serde(rename = "type")]
+    pub kind: OwnerKind,
+    /// The display name, `null` if the user never set one.
+    pub name: Option<String>,
+    pub company: Option<String>,
+    /// The website, which GitHub returns as an empty string rather than `null` if it isn't set.
+    pub blog: Option<String>,
+    pub location: Option<String>,
+    pub bio: Option<String>,
+    pub public_repos: u64,
+    pub followers: u64,
+    pub following: u64,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}